@@ -0,0 +1,231 @@
+use std::fmt::Display;
+
+use godot::builtin::Vector2i;
+
+use crate::CellRules;
+
+const CODE_PREFIX: &str = "TW";
+const CODE_VERSION: u8 = 1;
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+#[derive(Debug)]
+pub enum CodeError{
+    NotABlueprintCode,
+    UnsupportedVersion(u8),
+    ChecksumMismatch,
+    Malformed,
+    TooLarge,
+    UnplaceableCell,
+}
+
+impl Display for CodeError{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self{
+            Self::NotABlueprintCode => f.write_str("that isn't a blueprint code"),
+            Self::UnsupportedVersion(v) => write!(f, "blueprint code version {} isn't supported", v),
+            Self::ChecksumMismatch => f.write_str("blueprint code is damaged or was edited (checksum mismatch)"),
+            Self::Malformed => f.write_str("blueprint code is damaged"),
+            Self::TooLarge => f.write_str("blueprint is too large to share"),
+            Self::UnplaceableCell => f.write_str("blueprint code contains cells that can't be placed"),
+        }
+    }
+}
+
+//cells and pivot are relative to the top left of the blueprint's bounding box
+pub struct BlueprintCode{
+    pub cells: Vec<(Vector2i, CellRules)>,
+    pub pivot: Vector2i,
+    pub size: Vector2i
+}
+
+impl BlueprintCode{
+    //moves cells and pivot so the bounding box starts at the origin
+    pub fn new(cells: Vec<(Vector2i, CellRules)>, pivot: Vector2i) -> Self{
+        let min = cells.iter().fold(pivot, |m, (p, _)| Vector2i::new(m.x.min(p.x), m.y.min(p.y)));
+        let max = cells.iter().fold(pivot, |m, (p, _)| Vector2i::new(m.x.max(p.x), m.y.max(p.y)));
+        Self {
+            cells: cells.into_iter().map(|(p, c)| (p - min, c)).collect(),
+            pivot: pivot - min,
+            size: max - min + Vector2i::new(1, 1)
+        }
+    }
+
+    //uses the middle of the cells' bounding box as the pivot
+    pub fn centered(cells: Vec<(Vector2i, CellRules)>) -> Self{
+        let Some((first, _)) = cells.first() else{
+            return Self::new(cells, Vector2i::ZERO);
+        };
+        let min = cells.iter().fold(*first, |m, (p, _)| Vector2i::new(m.x.min(p.x), m.y.min(p.y)));
+        let max = cells.iter().fold(*first, |m, (p, _)| Vector2i::new(m.x.max(p.x), m.y.max(p.y)));
+        let pivot = min + Vector2i::new((max.x - min.x) / 2, (max.y - min.y) / 2);
+        Self::new(cells, pivot)
+    }
+
+    pub fn encode(&self) -> Result<String, CodeError>{
+        if self.size.x > 256 || self.size.y > 256 || self.cells.len() > u16::MAX as usize{
+            return Err(CodeError::TooLarge);
+        }
+        let mut bytes = vec![
+            CODE_VERSION,
+            (self.size.x - 1) as u8,
+            (self.size.y - 1) as u8,
+            self.pivot.x as u8,
+            self.pivot.y as u8
+        ];
+        bytes.extend_from_slice(&(self.cells.len() as u16).to_le_bytes());
+        for (pos, cell_rules) in self.cells.iter(){
+            if !is_shareable(cell_rules){
+                return Err(CodeError::UnplaceableCell);
+            }
+            bytes.extend_from_slice(&[pos.x as u8, pos.y as u8, cell_rules.to_id() as u8]);
+        }
+        bytes.extend_from_slice(&fletcher16(&bytes).to_le_bytes());
+        Ok(format!("{}{}", CODE_PREFIX, encode_base64(&bytes)))
+    }
+
+    pub fn decode(code: &str) -> Result<Self, CodeError>{
+        let body = code.trim().strip_prefix(CODE_PREFIX).ok_or(CodeError::NotABlueprintCode)?;
+        let bytes = decode_base64(body).ok_or(CodeError::NotABlueprintCode)?;
+        if bytes.len() < 9{
+            return Err(CodeError::Malformed);
+        }
+        let (payload, checksum) = bytes.split_at(bytes.len() - 2);
+        if fletcher16(payload).to_le_bytes() != checksum{
+            return Err(CodeError::ChecksumMismatch);
+        }
+        if payload[0] != CODE_VERSION{
+            return Err(CodeError::UnsupportedVersion(payload[0]));
+        }
+        let size = Vector2i::new(payload[1] as i32 + 1, payload[2] as i32 + 1);
+        let pivot = Vector2i::new(payload[3] as i32, payload[4] as i32);
+        let count = u16::from_le_bytes([payload[5], payload[6]]) as usize;
+        let cell_bytes = &payload[7..];
+        if cell_bytes.len() != count * 3 || pivot.x >= size.x || pivot.y >= size.y{
+            return Err(CodeError::Malformed);
+        }
+        let mut cells: Vec<(Vector2i, CellRules)> = vec![];
+        for cell in cell_bytes.chunks(3){
+            let pos = Vector2i::new(cell[0] as i32, cell[1] as i32);
+            if pos.x >= size.x || pos.y >= size.y || cells.iter().any(|(p, _)| *p == pos){
+                return Err(CodeError::Malformed);
+            }
+            if cell[2] > 3{
+                return Err(CodeError::Malformed);
+            }
+            let cell_rules = CellRules::from_id(cell[2] as u16);
+            if !is_shareable(&cell_rules){
+                return Err(CodeError::UnplaceableCell);
+            }
+            cells.push((pos, cell_rules));
+        }
+        Ok(Self { cells, pivot, size })
+    }
+}
+
+//cells a blueprint can hold, both sides of the code check this so anything exported can be imported again
+fn is_shareable(cell_rules: &CellRules) -> bool{
+    matches!(cell_rules, CellRules::Empty | CellRules::BasicFilled | CellRules::PermaCell)
+}
+
+fn fletcher16(bytes: &[u8]) -> u16{
+    let mut a: u16 = 0;
+    let mut b: u16 = 0;
+    for byte in bytes{
+        a = (a + *byte as u16) % 255;
+        b = (b + a) % 255;
+    }
+    (b << 8) | a
+}
+
+fn encode_base64(bytes: &[u8]) -> String{
+    let mut out = String::new();
+    for chunk in bytes.chunks(3){
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..chunk.len() + 1{
+            out.push(BASE64_ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+        }
+    }
+    out
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>>{
+    let mut out = vec![];
+    let mut n: u32 = 0;
+    let mut bits = 0;
+    for c in text.bytes(){
+        let value = BASE64_ALPHABET.iter().position(|a| *a == c)? as u32;
+        n = (n << 6) | value;
+        bits += 6;
+        if bits >= 8{
+            bits -= 8;
+            out.push((n >> bits) as u8);
+            n &= (1 << bits) - 1;
+        }
+    }
+    //whatever is left over is padding from the last byte, a whole character or set bits means the code was cut or edited
+    if bits >= 6 || n != 0{
+        return None;
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn sample() -> BlueprintCode{
+        BlueprintCode::new(vec![
+            (Vector2i::new(3, 2), CellRules::BasicFilled),
+            (Vector2i::new(4, 3), CellRules::BasicFilled),
+            (Vector2i::new(2, 4), CellRules::PermaCell),
+            (Vector2i::new(3, 4), CellRules::Empty)
+        ], Vector2i::new(3, 3))
+    }
+
+    #[test]
+    fn round_trip(){
+        let blueprint = sample();
+        let decoded = BlueprintCode::decode(&blueprint.encode().unwrap()).unwrap();
+        assert_eq!(decoded.cells, blueprint.cells);
+        assert_eq!(decoded.pivot, Vector2i::new(1, 1));
+        assert_eq!(decoded.size, Vector2i::new(3, 3));
+    }
+
+    #[test]
+    fn base64_round_trip(){
+        for len in 0..8{
+            let bytes: Vec<u8> = (0..len).map(|i| (i * 77 + 13) as u8).collect();
+            assert_eq!(decode_base64(&encode_base64(&bytes)), Some(bytes));
+        }
+    }
+
+    #[test]
+    fn edited_code_is_rejected(){
+        let code = sample().encode().unwrap();
+        let mut tampered: Vec<u8> = code.bytes().collect();
+        tampered[6] = if tampered[6] == b'A' { b'B' } else { b'A' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        assert!(matches!(BlueprintCode::decode(&tampered), Err(CodeError::ChecksumMismatch)));
+    }
+
+    #[test]
+    fn cut_code_is_rejected(){
+        let code = sample().encode().unwrap();
+        assert!(BlueprintCode::decode(&code[..code.len() - 1]).is_err());
+        assert!(BlueprintCode::decode(&code[..CODE_PREFIX.len()]).is_err());
+    }
+
+    #[test]
+    fn leftover_bits_are_rejected(){
+        //one byte takes two characters with four padding bits that have to be zero
+        assert_eq!(decode_base64("AQ"), Some(vec![1]));
+        assert_eq!(decode_base64("AR"), None);
+        assert_eq!(decode_base64("AAAAA"), None);
+    }
+
+    #[test]
+    fn force_empty_is_rejected_both_ways(){
+        let blueprint = BlueprintCode::new(vec![(Vector2i::ZERO, CellRules::ForceEmpty)], Vector2i::ZERO);
+        assert!(matches!(blueprint.encode(), Err(CodeError::UnplaceableCell)));
+    }
+}
@@ -0,0 +1,133 @@
+use godot::builtin::Dictionary;
+use godot::builtin::Rect2i;
+use godot::builtin::Variant;
+use godot::builtin::Vector2i;
+use godot::classes::ITileMapLayer;
+use godot::classes::Label;
+use godot::classes::TileMapLayer;
+use godot::obj::Base;
+use godot::obj::Gd;
+use godot::obj::WithBaseField;
+use godot::prelude::godot_api;
+use godot::prelude::GodotClass;
+
+use crate::cell_grid::CellGrid;
+use crate::CellEvents;
+use crate::CellRules;
+
+//isolated grid next to the blueprint editor that runs a blueprint without touching the DefenseLayer,
+//it starts empty, so perma cells and live cells already on the board play no part in the run
+#[derive(GodotClass)]
+#[class(base = TileMapLayer, init)]
+pub struct BlueprintSandbox{
+    base: Base<TileMapLayer>,
+    #[export]
+    rect: Rect2i,
+    #[export]
+    update_phys_interval: i32,
+    phys_clock: i32,
+    #[export]
+    max_generations: i32,
+    #[export]
+    report_label: Option<Gd<Label>>,
+    grid: Option<CellGrid>,
+    generation: i32,
+    event_totals: [i32; 3]
+}
+
+#[godot_api]
+impl ITileMapLayer for BlueprintSandbox {
+    fn physics_process(&mut self, _delta: f64){
+        if self.grid.is_none(){
+            return;
+        }
+        self.phys_clock += 1;
+        if self.phys_clock >= self.update_phys_interval{
+            self.phys_clock = 0;
+            self.simulate_generation();
+        }
+    }
+}
+
+#[godot_api]
+impl BlueprintSandbox{
+    //emitted once per generation with how many of each event fired, keyed by event name
+    #[signal]
+    fn generation_simulated(generation: i32, events: Dictionary);
+
+    #[func]
+    pub fn stop(&mut self){
+        if self.grid.take().is_some(){
+            self.base_mut().clear();
+            if let Some(mut label) = self.get_report_label(){
+                label.set_text("".into());
+            }
+        }
+    }
+}
+
+impl BlueprintSandbox{
+    //cells are relative to the blueprint's pivot, which gets placed in the middle of rect
+    pub fn run(&mut self, cells: Vec<(Vector2i, CellRules)>){
+        let center = self.rect.position + Vector2i::new(self.rect.size.x / 2, self.rect.size.y / 2);
+        let mut grid = CellGrid::empty(self.rect);
+        for (pos, cell_rules) in cells{
+            grid.set(pos + center, cell_rules);
+        }
+        self.grid = Some(grid);
+        self.generation = 0;
+        self.phys_clock = 0;
+        self.event_totals = [0; 3];
+        self.draw_grid();
+        self.update_report(&[0; 3]);
+    }
+    fn simulate_generation(&mut self){
+        let Some(grid) = self.grid.as_ref() else{
+            return;
+        };
+        let (next, events) = grid.step();
+        let mut counts = [0; 3];
+        for (_, e) in events{
+            counts[e.get_event_index()] += 1;
+        }
+        for i in 0..counts.len(){
+            self.event_totals[i] += counts[i];
+        }
+        self.grid = Some(next);
+        self.generation += 1;
+        self.draw_grid();
+        self.update_report(&counts);
+
+        let mut events = Dictionary::new();
+        for e in CellEvents::ALL{
+            events.set(e.get_event_name(), counts[e.get_event_index()]);
+        }
+        let generation = self.generation;
+        self.base_mut().emit_signal("generation_simulated".into(), &[Variant::from(generation), Variant::from(events)]);
+
+        if self.max_generations > 0 && self.generation >= self.max_generations{
+            self.grid = None;
+        }
+    }
+    fn draw_grid(&mut self){
+        let Some(grid) = self.grid.clone() else{
+            return;
+        };
+        for (pos, cell_rules) in grid.iter(){
+            if cell_rules.can_set(){
+                self.base_mut().set_cell_ex(pos).source_id(0).atlas_coords(cell_rules.to_atlas_coords()).done();
+            }
+        }
+    }
+    fn update_report(&self, counts: &[i32; 3]){
+        let Some(mut label) = self.get_report_label() else{
+            return;
+        };
+        let mut text = format!("generation {}", self.generation);
+        for e in CellEvents::ALL{
+            let i = e.get_event_index();
+            text += &format!("\n{}: {} ({} total)", e.get_event_name(), counts[i], self.event_totals[i]);
+        }
+        label.set_text(text.into());
+    }
+}
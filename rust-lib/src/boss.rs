@@ -0,0 +1,196 @@
+use godot::builtin::Array;
+use godot::builtin::Variant;
+use godot::classes::INode;
+use godot::classes::IProgressBar;
+use godot::classes::Node;
+use godot::classes::Node2D;
+use godot::classes::PackedScene;
+use godot::classes::PathFollow2D;
+use godot::classes::ProgressBar;
+use godot::classes::Resource;
+use godot::obj::Base;
+use godot::obj::Gd;
+use godot::obj::WithBaseField;
+use godot::prelude::godot_api;
+use godot::prelude::GodotClass;
+
+use crate::defense_layer;
+use crate::defense_layer::DefenseLayer;
+use crate::enemy_components::find_component;
+use crate::Damageable;
+
+const BOSS_GROUP: &str = "bosses";
+pub const PHASE_CHANGED_SIGNAL: &str = "phase_changed";
+
+//what a boss does once its health drops to health_fraction of max_health
+#[derive(GodotClass)]
+#[class(base = Resource, init)]
+pub struct BossPhase{
+    base: Base<Resource>,
+    #[export]
+    health_fraction: f32,
+    //speed is multiplied by this for burst_duration seconds every burst_interval seconds,
+    //or all the time if burst_interval is 0
+    #[export]
+    speed_multiplier: f32,
+    #[export]
+    burst_duration: f64,
+    #[export]
+    burst_interval: f64,
+    //sent down the boss's path from where the boss is every minion_interval seconds
+    #[export]
+    minion_scene: Option<Gd<PackedScene>>,
+    #[export]
+    minion_count: i32,
+    #[export]
+    minion_interval: f64,
+    //live cells within corrupt_radius tiles are killed every corrupt_interval seconds, 0 radius to turn off
+    #[export]
+    corrupt_radius: i32,
+    #[export]
+    corrupt_interval: f64
+}
+
+//makes the enemy it's added to a boss, phases are checked in order and should go from highest health_fraction to lowest
+#[derive(GodotClass)]
+#[class(base = Node, init)]
+pub struct Boss{
+    base: Base<Node>,
+    #[export]
+    phases: Array<Gd<BossPhase>>,
+    phase: Option<usize>,
+    burst_timer: f64,
+    minion_timer: f64,
+    corrupt_timer: f64
+}
+
+#[godot_api]
+impl INode for Boss{
+    fn ready(&mut self){
+        let mut enemy = self.base().get_parent().expect("boss has no enemy");
+        enemy.add_to_group(BOSS_GROUP.into());
+    }
+    fn physics_process(&mut self, delta: f64){
+        let enemy = self.base().get_parent().unwrap();
+        let Some(damageable) = find_component::<Damageable>(&enemy) else{
+            return;
+        };
+        let fraction = {
+            let damageable = damageable.bind();
+            damageable.get_current_health() as f32 / damageable.get_max_health().max(1) as f32
+        };
+        let phase = self.phases.iter_shared()
+            .enumerate()
+            .filter(|(_, phase)| fraction <= phase.bind().get_health_fraction())
+            .last()
+            .map(|(index, _)| index);
+        if phase != self.phase{
+            self.phase = phase;
+            self.burst_timer = 0.;
+            self.minion_timer = 0.;
+            self.corrupt_timer = 0.;
+            if let Some(index) = phase{
+                self.base_mut().emit_signal(PHASE_CHANGED_SIGNAL.into(), &[Variant::from(index as i32)]);
+            }
+        }
+        let Some(phase) = self.get_current_phase() else{
+            return;
+        };
+        let phase = phase.bind();
+        self.burst_timer += delta;
+        if phase.get_burst_interval() > 0. && self.burst_timer >= phase.get_burst_interval(){
+            self.burst_timer -= phase.get_burst_interval();
+        }
+        self.minion_timer += delta;
+        if phase.get_minion_interval() > 0. && self.minion_timer >= phase.get_minion_interval(){
+            self.minion_timer = 0.;
+            self.spawn_minions(&enemy, &phase);
+        }
+        self.corrupt_timer += delta;
+        if phase.get_corrupt_interval() > 0. && self.corrupt_timer >= phase.get_corrupt_interval(){
+            self.corrupt_timer = 0.;
+            self.corrupt(&enemy, phase.get_corrupt_radius());
+        }
+    }
+}
+
+#[godot_api]
+impl Boss{
+    #[signal]
+    fn phase_changed(index: i32);
+
+    //-1 before the first phase starts
+    #[func]
+    pub fn get_phase(&self) -> i32{
+        self.phase.map_or(-1, |index| index as i32)
+    }
+}
+
+impl Boss{
+    //what the boss's speed gets multiplied by right now
+    pub fn speed_multiplier(&self) -> f32{
+        let Some(phase) = self.get_current_phase() else{
+            return 1.;
+        };
+        let phase = phase.bind();
+        if phase.get_burst_interval() <= 0. || self.burst_timer < phase.get_burst_duration(){
+            phase.get_speed_multiplier()
+        }else{
+            1.
+        }
+    }
+    fn get_current_phase(&self) -> Option<Gd<BossPhase>>{
+        self.phase.and_then(|index| self.phases.get(index))
+    }
+    fn spawn_minions(&self, enemy: &Gd<Node>, phase: &BossPhase){
+        let Some(scene) = phase.get_minion_scene() else{
+            return;
+        };
+        let (Ok(enemy), Some(mut path)) = (enemy.clone().try_cast::<PathFollow2D>(), enemy.get_parent()) else{
+            return;
+        };
+        for _ in 0..phase.get_minion_count(){
+            let mut minion: Gd<PathFollow2D> = scene.instantiate().expect("failed to create enemy").cast();
+            minion.set_progress(enemy.get_progress());
+            //minions join the enemy group in their ready, and the deferred add happens before the next
+            //physics frame, so a wave can't be counted as cleared while they are on their way in
+            path.call_deferred("add_child".into(), &[Variant::from(minion)]);
+        }
+    }
+    fn corrupt(&self, enemy: &Gd<Node>, radius: i32){
+        let (Ok(enemy), Some(mut layer)) = (enemy.clone().try_cast::<Node2D>(), DefenseLayer::find(self.base().get_tree().unwrap())) else{
+            return;
+        };
+        let center = defense_layer::tile_at(enemy.get_global_position());
+        let tiles = layer.bind().killable_cells(center, radius);
+        layer.bind_mut().kill_cells(&tiles);
+    }
+}
+
+//shows the health of the first living boss, hidden while there is none
+#[derive(GodotClass)]
+#[class(base = ProgressBar, init)]
+pub struct BossHealthBar{
+    base: Base<ProgressBar>
+}
+
+#[godot_api]
+impl IProgressBar for BossHealthBar{
+    fn ready(&mut self){
+        self.base_mut().set_visible(false);
+    }
+    fn process(&mut self, _delta: f64){
+        let boss = self.base().get_tree().unwrap().get_first_node_in_group(BOSS_GROUP.into());
+        let Some(damageable) = boss.and_then(|boss| find_component::<Damageable>(&boss)) else{
+            self.base_mut().set_visible(false);
+            return;
+        };
+        let (health, max_health) = {
+            let damageable = damageable.bind();
+            (damageable.get_current_health(), damageable.get_max_health())
+        };
+        self.base_mut().set_max(max_health as f64);
+        self.base_mut().set_value(health.max(0) as f64);
+        self.base_mut().set_visible(true);
+    }
+}
@@ -0,0 +1,129 @@
+use godot::builtin::Callable;
+use godot::builtin::Color;
+use godot::builtin::Rect2;
+use godot::builtin::Vector2i;
+use godot::classes::BaseButton;
+use godot::classes::INode2D;
+use godot::classes::Input;
+use godot::classes::Node2D;
+use godot::classes::TileMapLayer;
+use godot::obj::Base;
+use godot::obj::Gd;
+use godot::obj::WithBaseField;
+use godot::prelude::godot_api;
+use godot::prelude::GodotClass;
+
+use crate::cell_patterns::CellPatternToolbox;
+use crate::defense_layer::get_mouse_tile;
+use crate::defense_layer::TILE_SIZE;
+use crate::ingame_state_tracker::GameplayState;
+use crate::ingame_state_tracker::IngameStateTracker;
+use crate::CellRules;
+
+//drag a rectangle over the board to turn the live cells in it into a new blueprint
+#[derive(GodotClass)]
+#[class(base = Node2D, init)]
+pub struct CaptureTool{
+    base: Base<Node2D>,
+    #[export]
+    target: Option<Gd<TileMapLayer>>,
+    #[export]
+    toolbox: Option<Gd<CellPatternToolbox>>,
+    #[export]
+    game_state: Option<Gd<IngameStateTracker>>,
+    #[export]
+    toggle_button: Option<Gd<BaseButton>>,
+    active: bool,
+    anchor: Option<Vector2i>,
+    corner: Vector2i
+}
+
+#[godot_api]
+impl INode2D for CaptureTool {
+    fn ready(&mut self){
+        if let Some(mut button) = self.get_toggle_button(){
+            button.connect("pressed".into(), Callable::from_object_method(&self.to_gd(), "toggle"));
+        }
+    }
+    fn process(&mut self, _delta: f64){
+        let state = self.get_game_state().expect("no game state ref was set").bind().get_state();
+        let can_capture = state == GameplayState::DRAWING || state == GameplayState::DEFENDING;
+        if let Some(mut button) = self.get_toggle_button(){
+            button.set_visible(can_capture);
+        }
+        if !can_capture{
+            if self.active{
+                self.toggle();
+            }
+            return;
+        }
+        //exact so ctrl+c for copying a blueprint doesn't toggle it too
+        if Input::singleton().is_action_just_pressed_ex("toggle_capture".into()).exact_match(true).done(){
+            self.toggle();
+        }
+        if !self.active{
+            return;
+        }
+        let mouse_tile = get_mouse_tile(self.base().get_viewport().expect("no valid viewport"));
+        let input = Input::singleton();
+        if input.is_action_just_pressed("place_pattern".into()){
+            self.anchor = Some(mouse_tile);
+        }
+        let Some(anchor) = self.anchor else{
+            return;
+        };
+        if self.corner != mouse_tile{
+            self.corner = mouse_tile;
+            self.base_mut().queue_redraw();
+        }
+        if !input.is_action_pressed("place_pattern".into()){
+            self.capture(anchor, mouse_tile);
+            self.toggle();
+        }
+    }
+    fn draw(&mut self){
+        let Some(anchor) = self.anchor else{
+            return;
+        };
+        let (min, max) = Self::corners(anchor, self.corner);
+        let rect = Rect2::new(min.cast_float() * TILE_SIZE, (max - min + Vector2i::new(1, 1)).cast_float() * TILE_SIZE);
+        self.base_mut().draw_rect_ex(rect, Color::from_rgba(0.4, 0.8, 1., 0.8)).filled(false).width(4.).done();
+    }
+}
+
+#[godot_api]
+impl CaptureTool{
+    #[func]
+    pub fn toggle(&mut self){
+        self.active = !self.active;
+        self.anchor = None;
+        self.base_mut().queue_redraw();
+    }
+    #[func]
+    pub fn capture(&mut self, from: Vector2i, to: Vector2i){
+        let target = self.get_target().unwrap();
+        let (min, max) = Self::corners(from, to);
+        let mut cells = vec![];
+        for y in min.y..=max.y{
+            for x in min.x..=max.x{
+                let pos = Vector2i::new(x, y);
+                if CellRules::from_tile(target.get_cell_tile_data(pos)) == CellRules::BasicFilled{
+                    cells.push((pos, CellRules::BasicFilled));
+                }
+            }
+        }
+        if cells.is_empty(){
+            return;
+        }
+        self.get_toolbox().unwrap().bind_mut().add_pattern(cells);
+    }
+}
+
+impl CaptureTool{
+    pub fn is_active(&self) -> bool{
+        self.active
+    }
+    fn corners(a: Vector2i, b: Vector2i) -> (Vector2i, Vector2i){
+        (Vector2i::new(a.x.min(b.x), a.y.min(b.y)), Vector2i::new(a.x.max(b.x), a.y.max(b.y)))
+    }
+}
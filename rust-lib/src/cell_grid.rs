@@ -0,0 +1,138 @@
+use godot::builtin::Rect2i;
+use godot::builtin::Vector2i;
+
+use crate::draw_tools::rect_contains;
+use crate::CellEvents;
+use crate::CellRules;
+
+const NEIGHBOR_COORDS: [(i32, i32); 8] = [
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, 1)
+];
+
+//snapshot of the cells inside rect, anything outside of it counts as ForceEmpty
+#[derive(Clone)]
+pub struct CellGrid{
+    rect: Rect2i,
+    cells: Vec<CellRules>
+}
+
+impl CellGrid{
+    pub fn new(rect: Rect2i, read: impl Fn(Vector2i) -> CellRules) -> Self{
+        let mut cells = Vec::new();
+        for y in 0..rect.size.y{
+            for x in 0..rect.size.x{
+                cells.push(read(Vector2i::new(x, y) + rect.position));
+            }
+        }
+        Self { rect, cells }
+    }
+    pub fn empty(rect: Rect2i) -> Self{
+        Self::new(rect, |_| CellRules::Empty)
+    }
+    pub fn rect(&self) -> Rect2i{
+        self.rect
+    }
+    pub fn get(&self, pos: Vector2i) -> CellRules{
+        if !rect_contains(self.rect, pos){
+            return CellRules::ForceEmpty;
+        }
+        self.cells[self.index(pos)].clone()
+    }
+    pub fn set(&mut self, pos: Vector2i, cell: CellRules){
+        if rect_contains(self.rect, pos){
+            let i = self.index(pos);
+            self.cells[i] = cell;
+        }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (Vector2i, &CellRules)>{
+        let rect = self.rect;
+        self.cells.iter().enumerate().map(move |(i, cell)|{
+            let i = i as i32;
+            (Vector2i::new(i % rect.size.x, i / rect.size.x) + rect.position, cell)
+        })
+    }
+    //advances one generation, returning the next grid and every event fired along the way
+    pub fn step(&self) -> (CellGrid, Vec<(Vector2i, CellEvents)>){
+        let mut next = self.clone();
+        let mut events = vec![];
+        for (pos, cell_rules) in self.iter(){
+            let neighbors: Vec<CellRules> = NEIGHBOR_COORDS.iter().map(|(x, y)| self.get(pos + Vector2i::new(*x, *y))).collect();
+            for e in cell_rules.events(&neighbors){
+                events.push((pos, e));
+            }
+            let t = cell_rules.next_cell(&neighbors);
+            if t.can_set(){
+                next.set(pos, t);
+            }
+        }
+        (next, events)
+    }
+    fn index(&self, pos: Vector2i) -> usize{
+        let local = pos - self.rect.position;
+        (local.x + local.y * self.rect.size.x) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    fn grid_with(size: i32, live: &[(i32, i32)]) -> CellGrid{
+        let mut grid = CellGrid::empty(Rect2i::new(Vector2i::ZERO, Vector2i::new(size, size)));
+        for (x, y) in live{
+            grid.set(Vector2i::new(*x, *y), CellRules::BasicFilled);
+        }
+        grid
+    }
+
+    fn live_cells(grid: &CellGrid) -> Vec<Vector2i>{
+        grid.iter().filter(|(_, cell)| cell.is_alive()).map(|(pos, _)| pos).collect()
+    }
+
+    #[test]
+    fn outside_rect_is_force_empty(){
+        let grid = grid_with(3, &[]);
+        assert_eq!(grid.get(Vector2i::new(-1, 0)), CellRules::ForceEmpty);
+        assert_eq!(grid.get(Vector2i::new(3, 3)), CellRules::ForceEmpty);
+        assert_eq!(grid.get(Vector2i::new(1, 1)), CellRules::Empty);
+    }
+
+    #[test]
+    fn block_stays_still(){
+        let grid = grid_with(4, &[(1, 1), (2, 1), (1, 2), (2, 2)]);
+        let (next, _) = grid.step();
+        assert_eq!(live_cells(&next), live_cells(&grid));
+    }
+
+    #[test]
+    fn blinker_flips_every_generation(){
+        let grid = grid_with(5, &[(1, 2), (2, 2), (3, 2)]);
+        let (next, _) = grid.step();
+        assert_eq!(live_cells(&next), vec![Vector2i::new(2, 1), Vector2i::new(2, 2), Vector2i::new(2, 3)]);
+        let (back, _) = next.step();
+        assert_eq!(live_cells(&back), live_cells(&grid));
+    }
+
+    #[test]
+    fn overcrowded_cell_fires_overpopulate_death(){
+        let grid = grid_with(5, &[(2, 2), (1, 2), (3, 2), (2, 1), (2, 3)]);
+        let (next, events) = grid.step();
+        assert!(events.contains(&(Vector2i::new(2, 2), CellEvents::OverpopulateDeath)));
+        assert_eq!(next.get(Vector2i::new(2, 2)), CellRules::Empty);
+    }
+
+    #[test]
+    fn perma_cells_never_die(){
+        let mut grid = grid_with(3, &[]);
+        grid.set(Vector2i::new(1, 1), CellRules::PermaCell);
+        let (next, _) = grid.step();
+        assert_eq!(next.get(Vector2i::new(1, 1)), CellRules::PermaCell);
+    }
+}
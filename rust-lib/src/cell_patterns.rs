@@ -3,36 +3,95 @@ use std::ops::Add;
 
 use godot::builtin::Array;
 use godot::builtin::Callable;
+use godot::builtin::Color;
+use godot::builtin::Dictionary;
+use godot::builtin::GString;
+use godot::builtin::Rect2;
 use godot::builtin::Rect2i;
 use godot::builtin::Vector2;
+use godot::builtin::Variant;
 use godot::builtin::Vector2i;
 use godot::classes::BaseButton;
 use godot::classes::CanvasLayer;
 use godot::classes::Control;
+use godot::classes::DisplayServer;
 use godot::classes::ICanvasLayer;
 use godot::classes::INode;
 use godot::classes::ITileMapLayer;
 use godot::classes::Input;
 use godot::classes::InputEvent;
+use godot::classes::Label;
 use godot::classes::Node;
+use godot::classes::Node2D;
+use godot::classes::SceneTree;
 use godot::classes::TextureProgressBar;
 use godot::classes::TileMapLayer;
-use godot::classes::Viewport;
+use godot::global::godot_warn;
 use godot::obj::Base;
 use godot::obj::Gd;
+use godot::obj::NewGd;
 use godot::obj::WithBaseField;
 use godot::prelude::godot_api;
+use godot::prelude::Export;
 use godot::prelude::GodotClass;
+use godot::prelude::GodotConvert;
+use godot::prelude::Var;
 
+use crate::blueprint_code::BlueprintCode;
+use crate::blueprint_sandbox::BlueprintSandbox;
+use crate::capture_tool::CaptureTool;
+use crate::cost_table::CostTable;
+use crate::defense_layer::get_mouse_tile;
+use crate::defense_layer::DefenseLayer;
 use crate::defense_layer::TILE_SIZE;
+use crate::demolish_tool::DemolishTool;
+use crate::draw_tools;
+use crate::draw_tools::DrawTool;
+use crate::draw_tools::Symmetry;
+use crate::edit_history::EditHistory;
+use crate::enemy_spawner::EnemyPath;
 use crate::ingame_state_tracker::GameplayState;
 use crate::ingame_state_tracker::IngameStateTracker;
+use crate::ingame_state_tracker::START_DRAW_SIGNAL;
+use crate::pattern_analysis;
+use crate::player_progress::PlayerProgress;
+use crate::resource_wallet;
+use crate::resource_wallet::ResourceCost;
+use crate::resource_wallet::ResourcePool;
 use crate::selected_hotbar::SelectedHotbar;
 use crate::CellRules;
 
+pub const ENERGY_CHANGED_SIGNAL: &str = "energy_changed";
+pub const RESOURCE_CHANGED_SIGNAL: &str = "resource_changed";
+const PLAYER_ENERGY_GROUP: &str = "player_energy";
+
+//where a change in energy came from, sent along with energy_changed
+#[derive(Clone, Copy)]
+pub enum EnergyReason{
+    Timer,
+    Placement,
+    Refund,
+    Bounty,
+    WaveBonus,
+    Interest
+}
+
+impl EnergyReason{
+    fn get_name(&self) -> &str{
+        match self{
+            Self::Timer => "timer",
+            Self::Placement => "placement",
+            Self::Refund => "refund",
+            Self::Bounty => "bounty",
+            Self::WaveBonus => "wave_bonus",
+            Self::Interest => "interest"
+        }
+    }
+}
+
 #[derive(GodotClass)]
 #[class(base = Node,init)]
-struct PlayerEnergy {
+pub struct PlayerEnergy {
     base: Base<Node>,
     #[export]
     energy: i32,
@@ -43,14 +102,28 @@ struct PlayerEnergy {
     energy_interval: f64,
     #[export]
     energy_per_interval: i32,
+    //fraction of banked energy paid out at the start of every drawing phase, 0 for none
+    #[export]
+    interest_rate: f32,
+    //most energy a single interest payout can give, 0 for no limit
+    #[export]
+    max_interest: i32,
     #[export]
     display: Option<Gd<TextureProgressBar>>,
+    //resources other than energy, costs naming a resource that isn't here can't be paid
+    #[export]
+    pools: Array<Gd<ResourcePool>>,
     #[export]
     game_state: Option<Gd<IngameStateTracker>>
 }
 
 #[godot_api]
 impl INode for PlayerEnergy{
+    fn ready(&mut self){
+        self.base_mut().add_to_group(PLAYER_ENERGY_GROUP.into());
+        let mut game_state = self.get_game_state().expect("no game state ref was set");
+        game_state.connect(START_DRAW_SIGNAL.into(), Callable::from_object_method(&self.to_gd(), "pay_interest"));
+    }
     fn physics_process(&mut self, delta: f64){
         if self.get_game_state().expect("no game state ref was set").bind().get_state() != GameplayState::DEFENDING{
             return;
@@ -58,7 +131,7 @@ impl INode for PlayerEnergy{
         self.energy_timer += delta;
         if self.energy_timer >= self.energy_interval{
             if self.energy<self.max_energy{
-                self.gain_energy(self.energy_per_interval);
+                self.gain_energy(self.energy_per_interval, EnergyReason::Timer);
             }
             self.energy_timer = 0.;
         }
@@ -67,6 +140,12 @@ impl INode for PlayerEnergy{
 
 #[godot_api]
 impl PlayerEnergy {
+    #[signal]
+    fn energy_changed(old: i32, new: i32, reason: GString);
+    //sent for every resource, energy included
+    #[signal]
+    fn resource_changed(resource: GString, old: i32, new: i32, reason: GString);
+
     #[func]
     fn can_use(&self, cost: i32) -> bool {
         cost <= self.energy
@@ -74,23 +153,102 @@ impl PlayerEnergy {
     #[func]
     fn try_use(&mut self, cost: i32) -> bool {
         if cost <= self.energy {
-            self.energy -= cost;
-            self.display.clone().unwrap().set_value(self.energy as f64);
+            self.set_energy_for(self.energy - cost, EnergyReason::Placement);
             return true;
         }
         return false;
     }
     #[func]
-    fn gain_energy(&mut self, amount: i32){
-        self.energy += amount;
-        self.energy = self.energy.min(self.max_energy);
+    pub fn get_resource(&self, resource: GString) -> i32 {
+        self.get_amount(&resource.to_string())
+    }
+    //cost is {"resource name": amount}, like CellPattern::get_cost returns
+    #[func]
+    pub fn can_afford_cost(&self, cost: Dictionary) -> bool {
+        self.can_afford(&ResourceCost::from_dictionary(&cost))
+    }
+    #[func]
+    fn pay_interest(&mut self){
+        let mut interest = (self.energy as f32 * self.interest_rate).floor() as i32;
+        if self.max_interest > 0{
+            interest = interest.min(self.max_interest);
+        }
+        if interest > 0{
+            self.gain_energy(interest, EnergyReason::Interest);
+        }
+    }
+}
+
+impl PlayerEnergy {
+    pub fn gain_energy(&mut self, amount: i32, reason: EnergyReason){
+        self.set_energy_for((self.energy + amount).min(self.max_energy), reason);
+    }
+    pub fn gain(&mut self, resource: &str, amount: i32, reason: EnergyReason){
+        if resource == resource_wallet::ENERGY{
+            self.gain_energy(amount, reason);
+            return;
+        }
+        let Some(mut pool) = self.find_pool(resource) else{
+            godot_warn!("no resource pool named {}", resource);
+            return;
+        };
+        let old = pool.bind().get_amount();
+        let new = pool.bind_mut().set_amount_clamped(old + amount);
+        self.emit_resource_changed(resource, old, new, reason);
+    }
+    pub fn get_amount(&self, resource: &str) -> i32{
+        if resource == resource_wallet::ENERGY{
+            return self.energy;
+        }
+        self.find_pool(resource).map_or(0, |pool| pool.bind().get_amount())
+    }
+    pub fn can_afford(&self, cost: &ResourceCost) -> bool{
+        cost.iter().all(|(resource, amount)| *amount <= self.get_amount(resource))
+    }
+    //takes every resource in cost, or nothing if any of them is short
+    pub fn spend(&mut self, cost: &ResourceCost, reason: EnergyReason) -> bool{
+        if !self.can_afford(cost){
+            return false;
+        }
+        for (resource, amount) in cost.iter(){
+            if resource == resource_wallet::ENERGY{
+                self.set_energy_for(self.energy - amount, reason);
+            }else{
+                self.gain(resource, -amount, reason);
+            }
+        }
+        true
+    }
+    //the level's PlayerEnergy, for things spawned at runtime that can't hold a reference to it
+    pub fn find(tree: Gd<SceneTree>) -> Option<Gd<PlayerEnergy>>{
+        tree.get_first_node_in_group(PLAYER_ENERGY_GROUP.into()).map(|node| node.cast())
+    }
+    fn set_energy_for(&mut self, energy: i32, reason: EnergyReason){
+        let old = self.energy;
+        self.energy = energy;
         self.display.clone().unwrap().set_value(self.energy as f64);
+        if old != energy{
+            self.base_mut().emit_signal(ENERGY_CHANGED_SIGNAL.into(), &[Variant::from(old), Variant::from(energy), Variant::from(reason.get_name())]);
+            self.emit_resource_changed(resource_wallet::ENERGY, old, energy, reason);
+        }
+    }
+    fn emit_resource_changed(&mut self, resource: &str, old: i32, new: i32, reason: EnergyReason){
+        if old == new{
+            return;
+        }
+        self.base_mut().emit_signal(
+            RESOURCE_CHANGED_SIGNAL.into(),
+            &[Variant::from(resource), Variant::from(old), Variant::from(new), Variant::from(reason.get_name())]
+        );
+    }
+    fn find_pool(&self, resource: &str) -> Option<Gd<ResourcePool>>{
+        self.pools.iter_shared().find(|pool| pool.bind().get_resource_name().to_string() == resource)
     }
 }
 
 #[derive(GodotClass)]
 #[class(base = Node, init)]
-struct CellPatternToolbox {
+pub struct CellPatternToolbox {
     base: Base<Node>,
     #[export]
     patterns: Array<Gd<CellPattern>>,
@@ -98,6 +256,9 @@ struct CellPatternToolbox {
     selected_pattern: u8,
     #[export]
     brush_tiles: Array<u16>,
+    //brush tiles that stay out of brush_tiles until they're unlocked by finishing a level
+    #[export]
+    locked_brush_tiles: Array<u16>,
     selected_tile: u8,
     #[export]
     gamestate: Option<Gd<IngameStateTracker>>,
@@ -113,12 +274,42 @@ struct CellPatternToolbox {
     #[export]
     tile_picker: Option<Gd<SelectedHotbar>>,
     #[export]
-    blueprint_picker: Option<Gd<SelectedHotbar>>
+    blueprint_picker: Option<Gd<SelectedHotbar>>,
+    #[export]
+    cost_table: Option<Gd<CostTable>>,
+    #[export]
+    enemy_paths: Array<Gd<EnemyPath>>,
+    #[export]
+    placement_policy: PlacementPolicy,
+    #[export]
+    selected_tool: DrawTool,
+    #[export]
+    symmetry: Symmetry,
+    #[export]
+    switch_tool_button: Option<Gd<BaseButton>>,
+    #[export]
+    switch_symmetry_button: Option<Gd<BaseButton>>,
+    #[export]
+    tool_picker: Option<Gd<SelectedHotbar>>,
+    #[export]
+    symmetry_picker: Option<Gd<SelectedHotbar>>,
+    //most live cells a single blueprint may have, 0 for no limit
+    #[export]
+    max_live_cells: i32,
+    #[export]
+    sandbox: Option<Gd<BlueprintSandbox>>,
+    #[export]
+    test_run_button: Option<Gd<BaseButton>>,
+    #[export]
+    demolish_tool: Option<Gd<DemolishTool>>,
+    #[export]
+    capture_tool: Option<Gd<CaptureTool>>
 }
 
 #[godot_api]
 impl INode for CellPatternToolbox {
     fn ready(&mut self){
+        self.apply_unlocks();
         for _ in 0..self.patterns.len(){
             self.switch_next();
         }
@@ -127,13 +318,36 @@ impl INode for CellPatternToolbox {
         self.get_next_pattern_button().unwrap().connect("pressed".into(), Callable::from_object_method(&self.to_gd(), "switch_next"));
         self.get_prev_pattern_button().unwrap().connect("pressed".into(), Callable::from_object_method(&self.to_gd(), "switch_prev"));
         self.get_switch_brush_button().unwrap().connect("pressed".into(),Callable::from_object_method(&self.to_gd(), "switch_brush"));
-
+        if let Some(mut button) = self.get_switch_tool_button(){
+            button.connect("pressed".into(), Callable::from_object_method(&self.to_gd(), "switch_tool"));
+        }
+        if let Some(mut button) = self.get_switch_symmetry_button(){
+            button.connect("pressed".into(), Callable::from_object_method(&self.to_gd(), "switch_symmetry"));
+        }
+        if let Some(mut button) = self.get_test_run_button(){
+            button.connect("pressed".into(), Callable::from_object_method(&self.to_gd(), "test_run"));
+        }
+        self.update_tool_picker_display();
     }
     fn process(&mut self, _delta: f64) {
         let is_drawing = self.get_game_state().bind().get_state() == GameplayState::DRAWING;
         self.get_transparency_pane().unwrap().set_visible(is_drawing);
         self.get_tile_picker().unwrap().get_parent().unwrap().cast::<Control>().set_visible(is_drawing);
         self.get_switch_brush_button().unwrap().set_visible(is_drawing);
+        for mut button in [self.get_switch_tool_button(), self.get_switch_symmetry_button(), self.get_test_run_button()].into_iter().flatten(){
+            button.set_visible(is_drawing);
+        }
+        for picker in [self.get_tool_picker(), self.get_symmetry_picker()].into_iter().flatten(){
+            if let Some(mut options) = picker.get_parent().and_then(|parent| parent.try_cast::<Control>().ok()){
+                options.set_visible(is_drawing);
+            }
+        }
+        if let Some(mut sandbox) = self.get_sandbox(){
+            sandbox.set_visible(is_drawing);
+            if !is_drawing{
+                sandbox.bind_mut().stop();
+            }
+        }
     }
 }
 
@@ -160,10 +374,16 @@ impl CellPatternToolbox {
 
     #[func]
     pub fn switch_next(&mut self) {
+        if self.patterns.is_empty(){
+            return;
+        }
         self.switch_to(self.selected_pattern.add(1).rem_euclid(self.patterns.len() as u8));
     }
     #[func]
     pub fn switch_prev(&mut self) {
+        if self.patterns.is_empty(){
+            return;
+        }
         self.switch_to(
             self.selected_pattern
                 .add(self.patterns.len() as u8 - 1)
@@ -172,73 +392,324 @@ impl CellPatternToolbox {
     }
     #[func]
     pub fn switch_brush(&mut self){
+        if self.brush_tiles.is_empty(){
+            return;
+        }
         self.selected_tile += 1;
         self.selected_tile = self.selected_tile.rem_euclid(self.brush_tiles.len() as u8);
         self.update_tile_picker_display();
     }
 
+    #[func]
+    pub fn switch_tool(&mut self){
+        self.selected_tool = self.selected_tool.next();
+        self.update_tool_picker_display();
+    }
+    #[func]
+    pub fn switch_symmetry(&mut self){
+        self.symmetry = self.symmetry.next();
+        self.update_tool_picker_display();
+    }
+
+    //runs the selected blueprint in the sandbox grid
+    #[func]
+    pub fn test_run(&mut self){
+        let Some(mut sandbox) = self.get_sandbox() else{
+            return;
+        };
+        let Some(pattern) = self.patterns.get(self.selected_pattern as usize) else{
+            return;
+        };
+        let cells = pattern.bind().placed_cells(Vector2i::ZERO);
+        sandbox.bind_mut().run(cells);
+    }
+
+    //drops blueprints and brush tiles the player hasn't unlocked yet
+    fn apply_unlocks(&mut self){
+        let progress = PlayerProgress::load();
+        let (unlocked, locked): (Vec<Gd<CellPattern>>, Vec<Gd<CellPattern>>) = self.patterns.iter_shared()
+            .partition(|pattern| {
+                let unlock_id = pattern.bind().get_unlock_id();
+                unlock_id.is_empty() || progress.is_blueprint_unlocked(&unlock_id)
+            });
+        if unlocked.is_empty(){
+            godot_warn!("every blueprint is locked, leaving them all available");
+        }else{
+            for mut pattern in locked{
+                let displays = {
+                    let pattern = pattern.bind();
+                    [pattern.get_cooldown_display().map(|d| d.upcast::<Control>()), pattern.get_charges_label().map(|l| l.upcast::<Control>())]
+                };
+                for mut display in displays.into_iter().flatten(){
+                    display.set_visible(false);
+                }
+                pattern.queue_free();
+            }
+            self.patterns = unlocked.into_iter().collect();
+        }
+
+        let brush_tiles: Array<u16> = self.brush_tiles.iter_shared()
+            .filter(|tile| !self.locked_brush_tiles.contains(tile) || progress.is_brush_tile_unlocked(*tile))
+            .collect();
+        if brush_tiles.is_empty(){
+            godot_warn!("every brush tile is locked, leaving them all available");
+        }else{
+            self.brush_tiles = brush_tiles;
+        }
+
+        self.selected_pattern = self.selected_pattern.min((self.patterns.len() as u8).saturating_sub(1));
+        self.selected_tile = self.selected_tile.min((self.brush_tiles.len() as u8).saturating_sub(1));
+    }
+    fn update_tool_picker_display(&self){
+        if let Some(mut picker) = self.get_tool_picker(){
+            picker.bind_mut().set_selected(self.selected_tool.index());
+        }
+        if let Some(mut picker) = self.get_symmetry_picker(){
+            picker.bind_mut().set_selected(self.symmetry.index());
+        }
+    }
     fn update_tile_picker_display(&self){
         self.get_tile_picker().unwrap().bind_mut().set_selected(self.selected_tile as u32);
     }
     fn update_blueprint_picker_display(&self){
-        self.get_blueprint_picker().unwrap().bind_mut().set_selected(self.selected_pattern as u32);
+        let mut picker = self.get_blueprint_picker().unwrap();
+        let mut picker = picker.bind_mut();
+        picker.set_option_count(self.patterns.len() as u32);
+        picker.set_selected(self.selected_pattern as u32);
     }
 
+    //basic cells when the level has no brush tiles
     pub fn get_selected_brush_tile(&self) -> u16{
-        self.get_brush_tiles().get(self.selected_tile as usize).expect("invalid tile input")
+        self.get_brush_tiles().get(self.selected_tile as usize).unwrap_or(CellRules::BasicFilled.to_id())
+    }
+
+    //the level's cost table, or one with the default prices when none is set
+    pub fn get_cost_table_or_default(&self) -> Gd<CostTable>{
+        self.get_cost_table().unwrap_or_else(CostTable::new_gd)
     }
 
     pub fn get_game_state(&self)-> Gd<IngameStateTracker>{
         self.gamestate.clone().unwrap()
     }
+
+    //true while a tool that works on the board directly is in use, blueprints stop taking input then
+    pub fn is_board_tool_active(&self) -> bool{
+        self.demolish_tool.as_ref().is_some_and(|tool| tool.bind().is_active())
+            || self.capture_tool.as_ref().is_some_and(|tool| tool.bind().is_active())
+    }
+
+    //adds a blueprint made of cells, based off of the selected one, and selects it,
+    //cells that don't fit the selected blueprint's limits are turned down with an alert
+    pub fn add_pattern(&mut self, cells: Vec<(Vector2i, CellRules)>){
+        let Some(template) = self.patterns.get(self.selected_pattern as usize) else{
+            godot_warn!("there's no blueprint to base the new one on");
+            return;
+        };
+        let blueprint = BlueprintCode::centered(cells);
+        if let Err(error) = template.bind().check_fits(&blueprint, self.max_live_cells){
+            template.bind().alert(&error.to_uppercase());
+            return;
+        }
+        let mut pattern: Gd<CellPattern> = template.duplicate().expect("failed to duplicate blueprint").cast();
+        {
+            let mut pattern = pattern.bind_mut();
+            pattern.set_pattern_cells(blueprint);
+            pattern.set_cooldown_display(None);
+            pattern.set_charges_label(None);
+        }
+        self.base_mut().add_child(pattern.clone());
+        self.patterns.push(pattern);
+        self.switch_to(self.patterns.len() as u8 - 1);
+    }
+
+    pub fn is_near_enemy_path(&self, tile: Vector2i, radius: f32) -> bool{
+        let pos = (tile.cast_float() + Vector2::new(0.5, 0.5)) * TILE_SIZE;
+        for path in self.enemy_paths.iter_shared(){
+            let Some(curve) = path.get_curve() else{
+                continue;
+            };
+            let local = path.to_local(pos);
+            if curve.get_closest_point(local).distance_to(local) <= radius * TILE_SIZE{
+                return true;
+            }
+        }
+        false
+    }
+}
+
+//what to do when some cells of a blueprint land on tiles the player can't replace
+#[derive(GodotConvert, Var, Export, Default, PartialEq, Eq, Clone, Copy, Debug)]
+#[godot(via = i64)]
+pub enum PlacementPolicy {
+    #[default]
+    Reject = 0,
+    //place the cells that fit, only pay for those
+    PartialProRata = 1,
+    //place the cells that fit, pay for the whole blueprint
+    PartialFullCharge = 2,
+}
+
+struct PlacementReport {
+    placeable: Vec<(Vector2i, CellRules)>,
+    conflicts: Vec<(Vector2i, CellRules)>,
+    cost: ResourceCost,
+    policy: PlacementPolicy,
+}
+
+impl PlacementReport {
+    fn can_place(&self) -> bool{
+        if self.placeable.is_empty(){
+            return false;
+        }
+        self.policy != PlacementPolicy::Reject || self.conflicts.is_empty()
+    }
+    //why can_place is false
+    fn blocked_message(&self) -> &'static str{
+        if self.placeable.is_empty(){
+            "EVERY CELL OF THIS BLUEPRINT IS BLOCKED HERE"
+        }else{
+            "SOME CELLS OF THIS BLUEPRINT ARE BLOCKED HERE"
+        }
+    }
 }
 
 #[derive(GodotClass)]
 #[class(base = TileMapLayer, init)]
-struct CellPattern {
+pub struct CellPattern {
     base: Base<TileMapLayer>,
+    //id this blueprint is unlocked by in the player's progress, empty if it's always available
+    #[export]
+    unlock_id: GString,
     #[export]
     bounds: Rect2i,
+    //cell that ends up under the cursor when placing, kept inside bounds
+    #[export]
+    pivot: Vector2i,
+    //shown on the pivot cell of the hover preview
+    #[export]
+    pivot_marker: Option<Gd<Node2D>>,
     #[export]
     target: Option<Gd<TileMapLayer>>,
     #[export]
     preview: Option<Gd<TileMapLayer>>,
+    //layer used to show cells of the hovered placement that are blocked, tinted in the scene
+    #[export]
+    blocked_preview: Option<Gd<TileMapLayer>>,
     #[export]
     energy_source: Option<Gd<PlayerEnergy>>,
     last_mouse_pos: Vector2i,
+    hover_tile: Vector2i,
+    history: EditHistory,
+    shape_anchor: Option<Vector2i>,
     enabled: bool,
     #[export]
-    not_enough_resources_alert: Option<Gd<LayerHideAfter>>
+    not_enough_resources_alert: Option<Gd<LayerHideAfter>>,
+    //tells the player why a click didn't do anything
+    #[export]
+    message_alert: Option<Gd<LayerHideAfter>>,
+    //seconds after placing before this blueprint can be placed again, 0 for none
+    #[export]
+    cooldown: f64,
+    cooldown_timer: f64,
+    //how many times this blueprint can be placed per level, 0 for unlimited
+    #[export]
+    max_charges: i32,
+    charges_used: i32,
+    #[export]
+    cooldown_display: Option<Gd<TextureProgressBar>>,
+    #[export]
+    charges_label: Option<Gd<Label>>
 }
 
 #[godot_api]
 impl ITileMapLayer for CellPattern {
-    fn process(&mut self, _delta: f64) {
-        let parent: Gd<CellPatternToolbox> = self.base().get_parent().expect("no parent???").try_cast().expect("object is not a child of CellPatternToolbox"); 
+    fn process(&mut self, delta: f64) {
+        let parent = self.get_toolbox();
         let state = parent.bind().get_game_state().bind().get_state();
         if state == GameplayState::DRAWING{
             self.drawing_process();
-        }else if state == GameplayState::DEFENDING{
-            self.defending_process();
+            return;
+        }
+        self.history.clear();
+        if state == GameplayState::DEFENDING{
+            self.defending_process(delta);
         }
     }
     fn ready(&mut self){
         let enabled = self.enabled;
         self.base_mut().set_visible(enabled);
+        if !draw_tools::rect_contains(self.bounds, self.pivot){
+            self.pivot = self.bounds.position + Vector2i::new(self.bounds.size.x / 2, self.bounds.size.y / 2);
+        }
+        self.base_mut().queue_redraw();
+        self.update_status_display();
+    }
+    fn draw(&mut self){
+        let rect = Rect2::new(self.bounds.position.cast_float() * TILE_SIZE, self.bounds.size.cast_float() * TILE_SIZE);
+        self.base_mut().draw_rect_ex(rect, Color::from_rgba(1., 1., 1., 0.6)).filled(false).width(4.).done();
+        let pivot_rect = Rect2::new((self.pivot.cast_float() + Vector2::new(0.25, 0.25)) * TILE_SIZE, Vector2::new(0.5, 0.5) * TILE_SIZE);
+        self.base_mut().draw_rect_ex(pivot_rect, Color::from_rgba(1., 0.8, 0.2, 0.8)).filled(false).width(4.).done();
     }
     fn unhandled_input(&mut self, event: Gd<InputEvent>){
         if !self.enabled{
             return;
         }
-        let parent: Gd<CellPatternToolbox> = self.base().get_parent().expect("no parent???").try_cast().expect("object is not a child of CellPatternToolbox"); 
+        let parent = self.get_toolbox();
         let state = parent.bind().get_game_state().bind().get_state();
-        if state == GameplayState::DRAWING{
+        if state == GameplayState::DRAWING && !parent.bind().is_board_tool_active(){
             let mouse_tile =
-                Self::get_mouse_tile(self.base().get_viewport().expect("no valid viewport"));
+                get_mouse_tile(self.base().get_viewport().expect("no valid viewport"));
     
-            let valid = event.is_action_pressed("place_cell".into()) || (Input::singleton().is_action_pressed("place_cell".into()) && mouse_tile != self.last_mouse_pos);
-            if valid{
-                self.draw_cell(mouse_tile);
+            if event.is_action_pressed_ex("ui_undo".into()).exact_match(true).done(){
+                let cells = self.history.undo();
+                self.apply_history(&cells);
+                return;
+            }
+            if event.is_action_pressed_ex("ui_redo".into()).exact_match(true).done(){
+                let cells = self.history.redo();
+                self.apply_history(&cells);
+                return;
+            }
+            if event.is_action_pressed_ex("ui_copy".into()).exact_match(true).done(){
+                self.export_to_clipboard();
+                return;
+            }
+            if event.is_action_pressed_ex("ui_paste".into()).exact_match(true).done(){
+                let error = self.import_from_clipboard();
+                if !error.is_empty(){
+                    self.alert(&error.to_string().to_uppercase());
+                }
+                return;
+            }
+            if event.is_action_pressed("set_pivot".into()){
+                self.set_pivot_cell(mouse_tile);
+                return;
+            }
+            let pressed = event.is_action_pressed("place_cell".into());
+            let dragged = Input::singleton().is_action_pressed("place_cell".into()) && mouse_tile != self.last_mouse_pos;
+            let tool = parent.bind().get_selected_tool();
+            match tool{
+                DrawTool::Brush | DrawTool::Eraser => {
+                    if pressed || dragged{
+                        self.draw_cells(vec![mouse_tile]);
+                    }
+                }
+                DrawTool::FloodFill => {
+                    if pressed{
+                        let cells = self.flood_fill_cells(mouse_tile);
+                        self.draw_cells(cells);
+                    }
+                }
+                DrawTool::Line | DrawTool::Rectangle | DrawTool::FilledRectangle => {
+                    if pressed{
+                        self.shape_anchor = Some(mouse_tile);
+                    }
+                    if pressed || dragged{
+                        self.update_shape_preview(tool, mouse_tile);
+                    }
+                }
+            }
+            if pressed || dragged{
                 self.last_mouse_pos = mouse_tile;
             }
         }
@@ -246,10 +717,172 @@ impl ITileMapLayer for CellPattern {
 }
 
 impl CellPattern{
-    fn draw_cell(&mut self, tile: Vector2i){
-        let parent: Gd<CellPatternToolbox> = self.base().get_parent().expect("no parent???").try_cast().expect("object is not a child of CellPatternToolbox"); 
-        let r = CellRules::from_id(parent.bind().get_selected_brush_tile());
-        if r == CellRules::ForceEmpty{
+    fn get_toolbox(&self) -> Gd<CellPatternToolbox>{
+        self.base().get_parent().expect("no parent???").try_cast().expect("object is not a child of CellPatternToolbox")
+    }
+    //target layer positions and cell types of this pattern with its pivot on center
+    fn placed_cells(&self, center: Vector2i) -> Vec<(Vector2i, CellRules)>{
+        let offset = center - self.pivot;
+        self.base().get_used_cells().iter_shared().map(|cell_pos|{
+            let cell_rules = CellRules::from_tile(self.base().get_cell_tile_data(cell_pos));
+            (cell_pos + offset, cell_rules)
+        }).collect()
+    }
+    fn placement_report(&self, center: Vector2i) -> PlacementReport{
+        let target = self.get_target().expect("no target layer set");
+        let (placeable, conflicts): (Vec<_>, Vec<_>) = self.placed_cells(center).into_iter()
+            .partition(|(pos, _)| CellRules::from_tile(target.get_cell_tile_data(*pos)).user_replaceable());
+        let policy = self.get_toolbox().bind().get_placement_policy();
+        let cost = match policy{
+            PlacementPolicy::PartialProRata => self.cost_of(&placeable, true),
+            PlacementPolicy::Reject | PlacementPolicy::PartialFullCharge => {
+                self.cost_of(&placeable.iter().chain(conflicts.iter()).cloned().collect(), true)
+            }
+        };
+        PlacementReport { placeable, conflicts, cost, policy }
+    }
+    //total cost of the given cells including the per blueprint surcharge,
+    //on_board is false to leave out the discounts and surcharges that depend on where the cells land
+    fn cost_of(&self, cells: &Vec<(Vector2i, CellRules)>, on_board: bool) -> ResourceCost{
+        if cells.is_empty(){
+            return ResourceCost::default();
+        }
+        let surcharge = self.get_toolbox().bind().get_cost_table_or_default().bind().get_blueprint_surcharge();
+        let mut cost = self.cost_of_cells(cells, on_board);
+        cost.add(resource_wallet::ENERGY, surcharge);
+        cost
+    }
+    fn cost_of_cells(&self, cells: &Vec<(Vector2i, CellRules)>, on_board: bool) -> ResourceCost{
+        let toolbox = self.get_toolbox();
+        let toolbox = toolbox.bind();
+        let table = toolbox.get_cost_table_or_default();
+        let table = table.bind();
+        let target = self.get_target().filter(|_| on_board);
+
+        let mut cost = 0.;
+        let mut extra_cost = ResourceCost::default();
+        for (pos, cell_rules) in cells.iter() {
+            extra_cost.merge(&table.cell_extra_cost(cell_rules));
+            let mut cell_cost = table.cell_cost(cell_rules) as f32;
+            let overwrites_live_cell = target.as_ref()
+                .is_some_and(|t| CellRules::from_tile(t.get_cell_tile_data(*pos)) == CellRules::BasicFilled);
+            if overwrites_live_cell{
+                cell_cost *= 1. - table.get_overwrite_discount();
+            }
+            if on_board && toolbox.is_near_enemy_path(*pos, table.get_path_surcharge_radius()){
+                cell_cost += table.get_path_surcharge() as f32;
+            }
+            cost += cell_cost;
+        }
+        let mut total = ResourceCost::energy(cost.round() as i32);
+        total.merge(&extra_cost);
+        total
+    }
+    fn set_cells(mut layer: Gd<TileMapLayer>, cells: &Vec<(Vector2i, CellRules)>){
+        for (pos, cell_rules) in cells.iter() {
+            layer
+                .set_cell_ex(*pos)
+                .source_id(0)
+                .atlas_coords(cell_rules.to_atlas_coords())
+                .done();
+        }
+    }
+    fn clear_preview(&self){
+        self.get_preview().unwrap().clear();
+        if let Some(mut marker) = self.get_pivot_marker(){
+            marker.set_visible(false);
+        }
+        if let Some(mut blocked_preview) = self.get_blocked_preview(){
+            blocked_preview.clear();
+        }
+    }
+    fn alert(&self, message: &str){
+        if let Some(mut alert) = self.get_message_alert(){
+            alert.bind_mut().show_message(message);
+        }
+    }
+    fn get_brush_rules(&self) -> CellRules{
+        let parent = self.get_toolbox();
+        let parent = parent.bind();
+        if parent.get_selected_tool() == DrawTool::Eraser{
+            return CellRules::ForceEmpty;
+        }
+        CellRules::from_id(parent.get_selected_brush_tile())
+    }
+    //paints cells and their mirrored copies with the current brush
+    fn draw_cells(&mut self, cells: Vec<Vector2i>){
+        let symmetry = self.get_toolbox().bind().get_symmetry();
+        let r = self.get_brush_rules();
+        for tile in symmetry.mirror(cells, draw_tools::rect_center2(self.bounds)){
+            self.draw_cell(tile, r.clone());
+        }
+    }
+    fn draw_cell(&mut self, tile: Vector2i, r: CellRules){
+        if !self.can_draw_cell(tile, &r){
+            return;
+        }
+        let before = CellRules::from_tile(self.base().get_cell_tile_data(tile));
+        self.history.record(tile, before, r.clone());
+        self.write_cell(tile, &r);
+    }
+    //true if tile is inside bounds and r wouldn't go over max_live_cells
+    fn can_draw_cell(&self, tile: Vector2i, r: &CellRules) -> bool{
+        if !draw_tools::rect_contains(self.bounds, tile){
+            return false;
+        }
+        let before = CellRules::from_tile(self.base().get_cell_tile_data(tile));
+        let max_live_cells = self.get_toolbox().bind().get_max_live_cells();
+        !(max_live_cells > 0 && r.is_alive() && !before.is_alive() && self.count_live_cells() >= max_live_cells)
+    }
+    //writes undone or redone cells, skipping any the editor wouldn't let the player draw
+    fn apply_history(&mut self, cells: &Vec<(Vector2i, CellRules)>){
+        for (tile, r) in cells.iter(){
+            if self.can_draw_cell(*tile, r){
+                self.write_cell(*tile, r);
+            }
+        }
+    }
+    fn count_live_cells(&self) -> i32{
+        self.base().get_used_cells().iter_shared()
+            .filter(|p| CellRules::from_tile(self.base().get_cell_tile_data(*p)).is_alive())
+            .count() as i32
+    }
+    fn flood_fill_cells(&self, start: Vector2i) -> Vec<Vector2i>{
+        let start_rules = CellRules::from_tile(self.base().get_cell_tile_data(start));
+        draw_tools::flood_fill(start, self.bounds, |p| CellRules::from_tile(self.base().get_cell_tile_data(p)) == start_rules)
+    }
+    fn update_shape_preview(&self, tool: DrawTool, tile: Vector2i){
+        let Some(anchor) = self.shape_anchor else{
+            return;
+        };
+        let symmetry = self.get_toolbox().bind().get_symmetry();
+        let r = match self.get_brush_rules(){
+            CellRules::ForceEmpty => CellRules::Empty,
+            r => r
+        };
+        //cells outside of bounds get dropped when the shape is drawn, so they aren't previewed either
+        let cells: Vec<(Vector2i, CellRules)> = symmetry.mirror(tool.shape(anchor, tile), draw_tools::rect_center2(self.bounds))
+            .into_iter()
+            .filter(|p| draw_tools::rect_contains(self.bounds, *p))
+            .map(|p| (p, r.clone()))
+            .collect();
+        let mut preview = self.get_preview().unwrap();
+        preview.clear();
+        Self::set_cells(preview, &cells);
+    }
+    //draws the dragged out shape once the brush is released
+    fn finish_shape(&mut self){
+        let Some(anchor) = self.shape_anchor.take() else{
+            return;
+        };
+        let tool = self.get_toolbox().bind().get_selected_tool();
+        if tool.is_shape(){
+            self.draw_cells(tool.shape(anchor, self.last_mouse_pos));
+        }
+        self.get_preview().unwrap().clear();
+    }
+    fn write_cell(&mut self, tile: Vector2i, r: &CellRules){
+        if *r == CellRules::ForceEmpty{
             self.base_mut().set_cell(tile);
         }else{
             self.base_mut()
@@ -261,31 +894,100 @@ impl CellPattern{
     }
 
     //process method when player is defending
-    fn defending_process(&mut self){
+    fn defending_process(&mut self, delta: f64){
+        if self.cooldown_timer > 0.{
+            self.cooldown_timer = (self.cooldown_timer - delta).max(0.);
+            self.update_status_display();
+        }
         
         self.base_mut().set_visible(false);
+        let mouse_tile =
+            get_mouse_tile(self.base().get_viewport().expect("no valid viewport"));
+        self.hover_tile = mouse_tile;
         if !self.enabled {
             return;
         }
+        if self.get_toolbox().bind().is_board_tool_active(){
+            self.clear_preview();
+            return;
+        }
 
-        let cost = self.get_cost();
-        if !self.get_energy_source().unwrap().bind().can_use(cost){
+        let report = self.placement_report(mouse_tile);
+        if !self.get_energy_source().unwrap().bind().can_afford(&report.cost){
             if Input::singleton().is_action_just_pressed("place_pattern".into()){
                 let mut s = self.get_not_enough_resources_alert().unwrap();
                 s.bind_mut().reset_timer();
             }
             return;
         }
-        let mouse_tile =
-            Self::get_mouse_tile(self.base().get_viewport().expect("no valid viewport"));
         
         self.update_hover(self.preview.clone().unwrap(), mouse_tile);
 
         if Input::singleton().is_action_just_pressed("place_pattern".into()) {
-            if self.get_energy_source().unwrap().bind_mut().try_use(self.get_cost()){
-                self.place(self.target.clone().unwrap(), mouse_tile, true);
+            if !report.can_place(){
+                self.alert(report.blocked_message());
+            }else if self.get_charges_left() == 0{
+                self.alert("THIS BLUEPRINT HAS NO CHARGES LEFT");
+            }else if self.cooldown_timer > 0.{
+                self.alert("THIS BLUEPRINT IS STILL COOLING DOWN");
+            }else if self.get_energy_source().unwrap().bind_mut().spend(&report.cost, EnergyReason::Placement){
+                let target = self.target.clone().unwrap();
+                Self::set_cells(target.clone(), &report.placeable);
+                if let Ok(mut layer) = target.try_cast::<DefenseLayer>(){
+                    layer.bind_mut().mark_paid(&report.placeable, report.cost.get(resource_wallet::ENERGY));
+                }
+                self.use_charge();
             }
-            self.get_preview().unwrap().clear();
+            self.clear_preview();
+        }
+    }
+    //replaces the blueprint, moved so it sits around the middle of bounds
+    fn set_pattern_cells(&mut self, blueprint: BlueprintCode){
+        let size = blueprint.size;
+        let bounds_size = Vector2i::new(self.bounds.size.x.max(size.x), self.bounds.size.y.max(size.y));
+        let bounds_center = self.bounds.position + Vector2i::new(self.bounds.size.x / 2, self.bounds.size.y / 2);
+        self.bounds = Rect2i::new(bounds_center - Vector2i::new(bounds_size.x / 2, bounds_size.y / 2), bounds_size);
+        let offset = self.bounds.position + Vector2i::new((bounds_size.x - size.x) / 2, (bounds_size.y - size.y) / 2);
+
+        self.base_mut().clear();
+        self.history.clear();
+        for (pos, cell_rules) in blueprint.cells.iter(){
+            self.write_cell(*pos + offset, cell_rules);
+        }
+        self.pivot = blueprint.pivot + offset;
+        self.base_mut().queue_redraw();
+    }
+    fn try_import_code(&mut self, code: &str) -> Result<(), String>{
+        let blueprint = BlueprintCode::decode(code).map_err(|e| e.to_string())?;
+        let max_live_cells = self.get_toolbox().bind().get_max_live_cells();
+        self.check_fits(&blueprint, max_live_cells)?;
+        self.set_pattern_cells(blueprint);
+        Ok(())
+    }
+    //the limits every blueprint coming from outside the editor has to keep to, imported or captured
+    fn check_fits(&self, blueprint: &BlueprintCode, max_live_cells: i32) -> Result<(), String>{
+        if blueprint.size.x > self.bounds.size.x || blueprint.size.y > self.bounds.size.y{
+            return Err(format!("blueprint is {}x{}, this slot only fits {}x{}", blueprint.size.x, blueprint.size.y, self.bounds.size.x, self.bounds.size.y));
+        }
+        let live_cells = blueprint.cells.iter().filter(|(_, cell_rules)| cell_rules.is_alive()).count() as i32;
+        if max_live_cells > 0 && live_cells > max_live_cells{
+            return Err(format!("blueprint has {} live cells, the limit is {}", live_cells, max_live_cells));
+        }
+        Ok(())
+    }
+    fn use_charge(&mut self){
+        self.charges_used += 1;
+        self.cooldown_timer = self.cooldown;
+        self.update_status_display();
+    }
+    fn update_status_display(&self){
+        if let Some(mut display) = self.get_cooldown_display(){
+            display.set_max(self.cooldown);
+            display.set_value(self.cooldown_timer);
+        }
+        if let Some(mut label) = self.get_charges_label(){
+            let text = if self.max_charges > 0 { self.get_charges_left().to_string() } else { String::new() };
+            label.set_text(text.into());
         }
     }
     //process method when player is drawing new towers
@@ -295,6 +997,13 @@ impl CellPattern{
             return;
         }
         self.base_mut().set_visible(true);
+        if let Some(mut marker) = self.get_pivot_marker(){
+            marker.set_visible(false);
+        }
+        if !Input::singleton().is_action_pressed("place_cell".into()){
+            self.finish_shape();
+            self.history.end_stroke();
+        }
     }
 }
 
@@ -303,47 +1012,101 @@ impl CellPattern {
     fn set_enabled(&mut self, enabled: bool) {
         if self.enabled != enabled{
             self.enabled = enabled;
-            self.get_preview().unwrap().clear();
+            self.history.clear();
+            self.shape_anchor = None;
+            self.clear_preview();
         }
     }
-    fn get_mouse_tile(viewport: Gd<Viewport>) -> Vector2i {
-        let pos = viewport
-            .get_camera_2d()
-            .expect("no valid camera2d")
-            .get_global_mouse_position();
-        (pos / TILE_SIZE).floor().cast_int()
+    #[func]
+    pub fn is_ready_to_place(&self) -> bool {
+        self.cooldown_timer <= 0. && self.get_charges_left() != 0
     }
+    //-1 when the blueprint has unlimited charges
     #[func]
-    pub fn get_cost(&self) -> i32 {
-        let mut cost = 0;
-        let cells = self.base().get_used_cells();
-        for cell_pos in cells.iter_shared() {
-            let tile = self.base().get_cell_tile_data(cell_pos);
-            let cell_rules = CellRules::from_tile(tile);
-            cost += cell_rules.to_cost();
+    pub fn get_charges_left(&self) -> i32 {
+        if self.max_charges <= 0{
+            return -1;
         }
-        cost
+        (self.max_charges - self.charges_used).max(0)
     }
+    //cost of placing at the hovered tile as {"resource name": amount},
+    //while drawing there is nothing hovered so it's the cost before any board discounts or surcharges
     #[func]
-    pub fn get_center(&self) -> Vector2 {
-        let cells = self.base().get_used_cells();
-        let mut average = Vector2::new(0., 0.);
-        let mut count = 0;
-        for cell_pos in cells.iter_shared() {
-            count += 1;
-            average = average.lerp(cell_pos.cast_float(), 1. / count as f32);
+    pub fn get_cost(&self) -> Dictionary {
+        let state = self.get_toolbox().bind().get_game_state().bind().get_state();
+        if state == GameplayState::DRAWING{
+            return self.cost_of(&self.placed_cells(self.pivot), false).to_dictionary();
+        }
+        self.get_cost_at(self.hover_tile)
+    }
+    #[func]
+    pub fn get_cost_at(&self, center: Vector2i) -> Dictionary {
+        self.placement_report(center).cost.to_dictionary()
+    }
+    //returns {"valid": bool, "cost": Dictionary, "conflicts": Array[Vector2i]} for placing at center
+    #[func]
+    pub fn validate_placement(&self, center: Vector2i) -> Dictionary {
+        let report = self.placement_report(center);
+        let mut conflicts: Array<Vector2i> = Array::new();
+        for (pos, _) in report.conflicts.iter(){
+            conflicts.push(*pos);
+        }
+        let mut result = Dictionary::new();
+        result.set("valid", report.can_place());
+        result.set("cost", report.cost.to_dictionary());
+        result.set("conflicts", conflicts);
+        result
+    }
+    //runs the blueprint on its own for up to max_generations and classifies it,
+    //see PatternAnalysis::to_dictionary for the keys
+    #[func]
+    pub fn analyze(&self, max_generations: i32) -> Dictionary {
+        pattern_analysis::analyze(&self.placed_cells(Vector2i::ZERO), max_generations).to_dictionary()
+    }
+    #[func]
+    pub fn to_code(&self) -> GString {
+        let blueprint = BlueprintCode::new(self.placed_cells(self.pivot), self.pivot);
+        match blueprint.encode(){
+            Ok(code) => code.into(),
+            Err(e) => {
+                godot_warn!("can't share blueprint: {}", e);
+                GString::new()
+            }
+        }
+    }
+    #[func]
+    pub fn export_to_clipboard(&self) {
+        let code = self.to_code();
+        if !code.is_empty(){
+            DisplayServer::singleton().clipboard_set(code);
+        }
+    }
+    //returns an empty string on success, otherwise why the code was rejected
+    #[func]
+    pub fn import_code(&mut self, code: GString) -> GString {
+        match self.try_import_code(&code.to_string()){
+            Ok(()) => GString::new(),
+            Err(message) => {
+                godot_warn!("can't import blueprint: {}", message);
+                message.into()
+            }
+        }
+    }
+    #[func]
+    pub fn import_from_clipboard(&mut self) -> GString {
+        let code = DisplayServer::singleton().clipboard_get();
+        self.import_code(code)
+    }
+    #[func]
+    pub fn set_pivot_cell(&mut self, tile: Vector2i) {
+        if draw_tools::rect_contains(self.bounds, tile){
+            self.pivot = tile;
+            self.base_mut().queue_redraw();
         }
-        average
     }
     #[func]
     pub fn place(&self, mut target: Gd<TileMapLayer>, center: Vector2i, check_valid: bool) {
-        let cells_center = self.get_center();
-        let cells = self.base().get_used_cells();
-        for cell_pos in cells.iter_shared() {
-            let pos = cell_pos + center - cells_center.cast_int();
-            let cell_data = self.base().get_cell_tile_data(cell_pos);
-            let cell_rules = CellRules::from_tile(cell_data);
-
+        for (pos, cell_rules) in self.placed_cells(center) {
             let target_tile = CellRules::from_tile(target.get_cell_tile_data(pos));
             if target_tile.user_replaceable() || !check_valid {
                 target
@@ -357,16 +1120,30 @@ impl CellPattern {
     #[func]
     pub fn update_hover(&self, mut preview: Gd<TileMapLayer>, center: Vector2i) {
         preview.clear();
-        self.place(preview, center, false);
+        if let Some(mut marker) = self.get_pivot_marker(){
+            marker.set_visible(true);
+            marker.set_global_position((center.cast_float() + Vector2::new(0.5, 0.5)) * TILE_SIZE);
+        }
+        let Some(mut blocked_preview) = self.get_blocked_preview() else{
+            self.place(preview, center, false);
+            return;
+        };
+        blocked_preview.clear();
+        let report = self.placement_report(center);
+        Self::set_cells(preview, &report.placeable);
+        Self::set_cells(blocked_preview, &report.conflicts);
     }
 }
 
 #[derive(GodotClass)]
 #[class(base = CanvasLayer,init)]
-struct LayerHideAfter{
+pub struct LayerHideAfter{
     base: Base<CanvasLayer>,
     #[export]
     delay: f64,
+    //where show_message puts its text
+    #[export]
+    label: Option<Gd<Label>>,
     timer: f64
 }
 
@@ -383,6 +1160,12 @@ impl ICanvasLayer for LayerHideAfter {
 }
 
 impl LayerHideAfter{
+    pub fn show_message(&mut self, message: &str){
+        if let Some(mut label) = self.get_label(){
+            label.set_text(message.into());
+        }
+        self.reset_timer();
+    }
     pub fn reset_timer(&mut self){
         self.base_mut().set_visible(true);
         self.timer = self.delay;
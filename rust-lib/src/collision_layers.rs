@@ -0,0 +1,6 @@
+//physics layer bits shared by scenes and code, named in project.godot under layer_names
+
+//the Area2D of every enemy, the only thing projectiles look for
+pub const ENEMY_HURTBOX: u32 = 1 << 0;
+//projectile hitboxes, nothing looks for these so they stay off every mask
+pub const PROJECTILE: u32 = 1 << 1;
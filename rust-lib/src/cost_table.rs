@@ -0,0 +1,72 @@
+use godot::classes::IResource;
+use godot::classes::Resource;
+use godot::obj::Base;
+use godot::prelude::godot_api;
+use godot::prelude::GodotClass;
+
+use crate::resource_wallet;
+use crate::resource_wallet::ResourceCost;
+use crate::CellRules;
+
+//per level pricing for blueprints, set on the CellPatternToolbox
+#[derive(GodotClass)]
+#[class(base = Resource)]
+pub struct CostTable{
+    base: Base<Resource>,
+    #[export]
+    empty_cost: i32,
+    #[export]
+    basic_filled_cost: i32,
+    #[export]
+    perma_cell_cost: i32,
+    //seeds needed on top of energy for each perma cell
+    #[export]
+    perma_cell_seed_cost: i32,
+    //flat cost added once per placed blueprint
+    #[export]
+    blueprint_surcharge: i32,
+    //fraction of a cell's cost taken off when it lands on a cell that is already alive
+    #[export]
+    overwrite_discount: f32,
+    //extra cost per cell placed within path_surcharge_radius tiles of an enemy path
+    #[export]
+    path_surcharge: i32,
+    #[export]
+    path_surcharge_radius: f32
+}
+
+#[godot_api]
+impl IResource for CostTable {
+    fn init(base: Base<Resource>) -> Self{
+        Self{
+            base,
+            empty_cost: CellRules::Empty.to_cost(),
+            basic_filled_cost: CellRules::BasicFilled.to_cost(),
+            perma_cell_cost: CellRules::PermaCell.to_cost(),
+            perma_cell_seed_cost: CellRules::PermaCell.to_seed_cost(),
+            blueprint_surcharge: 0,
+            overwrite_discount: 0.,
+            path_surcharge: 0,
+            path_surcharge_radius: 0.
+        }
+    }
+}
+
+impl CostTable{
+    pub fn cell_cost(&self, cell: &CellRules) -> i32{
+        match cell{
+            CellRules::Empty => self.empty_cost,
+            CellRules::BasicFilled => self.basic_filled_cost,
+            CellRules::PermaCell => self.perma_cell_cost,
+            CellRules::ForceEmpty => 0
+        }
+    }
+    //resources other than energy the cell needs
+    pub fn cell_extra_cost(&self, cell: &CellRules) -> ResourceCost{
+        let mut cost = ResourceCost::default();
+        if *cell == CellRules::PermaCell{
+            cost.add(resource_wallet::SEEDS, self.perma_cell_seed_cost);
+        }
+        cost
+    }
+}
@@ -1,4 +1,5 @@
 use core::f64;
+use std::collections::HashMap;
 
 use godot::builtin::Array;
 use godot::builtin::Rect2i;
@@ -7,18 +8,21 @@ use godot::builtin::Vector2;
 use godot::builtin::Vector2i;
 use godot::classes::ITileMapLayer;
 use godot::classes::PackedScene;
+use godot::classes::SceneTree;
 use godot::classes::TileMapLayer;
+use godot::classes::Viewport;
 use godot::obj::Base;
 use godot::obj::Gd;
 use godot::obj::WithBaseField;
 use godot::prelude::godot_api;
 use godot::prelude::GodotClass;
 
+use crate::cell_grid::CellGrid;
 use crate::CellRules;
 
 #[derive(GodotClass)]
 #[class(base = TileMapLayer,init)]
-struct DefenseLayer{
+pub struct DefenseLayer{
     base: Base<TileMapLayer>,
     #[export]
     update_phys_interval: i32,
@@ -26,11 +30,16 @@ struct DefenseLayer{
     #[export]
     rect: Rect2i,
     #[export]
-    audio_scene_arr: Array<Gd<PackedScene>>
+    audio_scene_arr: Array<Gd<PackedScene>>,
+    //energy the player paid for each live cell they placed, cells the automaton grew aren't in here
+    paid_cells: HashMap<Vector2i, f32>
 }
 
 #[godot_api]
 impl ITileMapLayer for DefenseLayer {
+    fn ready(&mut self){
+        self.base_mut().add_to_group(DEFENSE_LAYER_GROUP.into());
+    }
     fn physics_process(&mut self, _delta: f64){
         self.phys_clock += 1;
         if self.phys_clock >= self.update_phys_interval{
@@ -48,69 +57,95 @@ impl DefenseLayer{
     fn extra_overpopulate_death(pos: Vector2);
     #[signal]
     fn cell_create(pos: Vector2);
+    //emitted after every automaton step, once the new cells are set
+    #[signal]
+    fn cells_updated();
+    //a cell killed by an enemy rather than by the automaton
+    #[signal]
+    fn cell_sapped(pos: Vector2);
 }
 
 impl DefenseLayer{
-    fn update_tiles(&mut self){
-        let rect = self.rect.clone();
-        let pos = rect.position;
-        let range = rect.size;
-
+    //the level's DefenseLayer, for enemies spawned at runtime
+    pub fn find(tree: Gd<SceneTree>) -> Option<Gd<DefenseLayer>>{
+        tree.get_first_node_in_group(DEFENSE_LAYER_GROUP.into()).map(|node| node.cast())
+    }
+    //closest cell an enemy can kill within range tiles of center, perma cells can't be killed
+    pub fn nearest_killable_cell(&self, center: Vector2i, range: i32) -> Option<Vector2i>{
+        self.killable_cells(center, range).into_iter().next()
+    }
+    //every cell an enemy can kill within range tiles of center, nearest first
+    pub fn killable_cells(&self, center: Vector2i, range: i32) -> Vec<Vector2i>{
         let mut cells = Vec::new();
-        for y in 0..range.y{
-            for x in 0..range.x{
-                let tile_pos = Vector2i::new(x, y)+pos;
-                let tile = self.base().get_cell_tile_data(tile_pos);
-                cells.push(CellRules::from_tile(tile));
+        for y in -range..=range{
+            for x in -range..=range{
+                let tile = center + Vector2i::new(x, y);
+                let distance = x * x + y * y;
+                if distance <= range * range && CellRules::from_tile(self.base().get_cell_tile_data(tile)) == CellRules::BasicFilled{
+                    cells.push((distance, tile));
+                }
             }
         }
-
-        //godot_print!("{:#?}",cells);
-
-        let neighbor_coords = vec![
-            Vector2i::new(1, 1),
-            Vector2i::new(1, 0),
-            Vector2i::new(1, -1),
-            Vector2i::new(0, -1),
-            Vector2i::new(-1, -1),
-            Vector2i::new(-1, 0),
-            Vector2i::new(-1, 1),
-            Vector2i::new(0, 1)
-        ];
+        cells.sort_by_key(|(distance, _)| *distance);
+        cells.into_iter().map(|(_, tile)| tile).collect()
+    }
+    //empties the cell so the next generation sees it as dead, returns false if there was nothing to kill
+    pub fn kill_cell(&mut self, tile: Vector2i) -> bool{
+        self.kill_cells(&[tile]) > 0
+    }
+    //kills every BasicFilled cell in tiles and returns how many there were,
+    //listeners hear cells_updated once for the whole batch
+    pub fn kill_cells(&mut self, tiles: &[Vector2i]) -> usize{
+        let mut killed = 0;
+        for tile in tiles{
+            if CellRules::from_tile(self.base().get_cell_tile_data(*tile)) != CellRules::BasicFilled{
+                continue;
+            }
+            self.base_mut().set_cell_ex(*tile).atlas_coords(CellRules::Empty.to_atlas_coords()).source_id(0).done();
+            self.paid_cells.remove(tile);
+            self.base_mut().emit_signal(CELL_SAPPED_SIGNAL.into(), &[Variant::from((tile.cast_float() + Vector2::new(0.5, 0.5)) * TILE_SIZE)]);
+            killed += 1;
+        }
+        if killed > 0{
+            self.base_mut().emit_signal(CELLS_UPDATED_SIGNAL.into(), &[]);
+        }
+        killed
+    }
+    //splits energy between the live cells that were just placed
+    pub fn mark_paid(&mut self, cells: &Vec<(Vector2i, CellRules)>, energy: i32){
+        let live: Vec<Vector2i> = cells.iter().filter(|(_, cell_rules)| cell_rules.is_alive()).map(|(pos, _)| *pos).collect();
+        for tile in live.iter(){
+            self.paid_cells.insert(*tile, energy as f32 / live.len() as f32);
+        }
+    }
+    //energy paid for the cell at tile, 0 if the player didn't place it
+    pub fn paid_for(&self, tile: Vector2i) -> f32{
+        self.paid_cells.get(&tile).copied().unwrap_or(0.)
+    }
+    pub fn forget_paid(&mut self, tile: Vector2i){
+        self.paid_cells.remove(&tile);
+    }
+    fn update_tiles(&mut self){
+        let grid = CellGrid::new(self.rect, |tile_pos| CellRules::from_tile(self.base().get_cell_tile_data(tile_pos)));
+        let (next, events) = grid.step();
 
         let mut c:Vec<u8> = vec![0,0,0];
 
-        for y in 0..range.y{
-            for x in 0..range.x{
-                let i = Self::map_vec_to_index(rect, Vector2i::new(x, y));
-                let mut neighbors = vec![];
-                for n in &neighbor_coords{
-                    let c = Vector2i::new(x, y) + *n;
-                    if c.x < 0 || c.x >= range.x{
-                        neighbors.push(CellRules::ForceEmpty);
-                    }else if c.y < 0 || c.y >= range.y {
-                        neighbors.push(CellRules::ForceEmpty);
-                    }else{
-                        neighbors.push(cells.get(Self::map_vec_to_index(rect,c)).unwrap().clone());
-                    }
-                }
-                let cell_rules = cells.get(i).unwrap().clone();
-                let events = cell_rules.events(&neighbors);
-                let tile_pos = Vector2i::new(x, y)+pos;
-                for e in events{
-                    self.base_mut().emit_signal(e.get_event_name().into(),&[Variant::from((tile_pos.cast_float() + Vector2::new(0.5, 0.5)) * TILE_SIZE)]);
-                    let event_index = e.get_event_index();
-                    let e = c.get(event_index).expect("event index out of range!");
-                    if *e < 4{
-                        c[event_index] = e + 1;
-                    }
-                }
-                let t = cell_rules.next_cell(&neighbors);
-                if t.can_set(){
-                    self.base_mut().set_cell_ex(tile_pos).atlas_coords(t.to_atlas_coords()).source_id(0).done();
-                }
+        for (tile_pos, e) in events{
+            self.base_mut().emit_signal(e.get_event_name().into(),&[Variant::from((tile_pos.cast_float() + Vector2::new(0.5, 0.5)) * TILE_SIZE)]);
+            let event_index = e.get_event_index();
+            let e = c.get(event_index).expect("event index out of range!");
+            if *e < 4{
+                c[event_index] = e + 1;
+            }
+        }
+        for (tile_pos, t) in next.iter(){
+            if t.can_set(){
+                self.base_mut().set_cell_ex(tile_pos).atlas_coords(t.to_atlas_coords()).source_id(0).done();
             }
         }
+        //a cell that died and grew back later belongs to the automaton
+        self.paid_cells.retain(|tile, _| next.get(*tile).is_alive());
 
         let audio = self.get_audio_scene_arr();
         for n in 0..c.len(){
@@ -123,13 +158,24 @@ impl DefenseLayer{
                 self.base_mut().add_child(s);
             }
         }
-    }
-    fn map_vec_to_index(rect: Rect2i,vec: Vector2i)-> usize{
-        let x = vec.x as usize;
-        let cy = (vec.y * rect.size.x) as usize;
-        x + cy
+        self.base_mut().emit_signal(CELLS_UPDATED_SIGNAL.into(), &[]);
     }
 }
 
+const DEFENSE_LAYER_GROUP: &str = "defense_layer";
+pub const CELLS_UPDATED_SIGNAL: &str = "cells_updated";
+pub const CELL_SAPPED_SIGNAL: &str = "cell_sapped";
 pub const TILE_TYPE_DATA_LAYER: &str = "tile_type";
-pub const TILE_SIZE: f32 = 64.;
\ No newline at end of file
+pub const TILE_SIZE: f32 = 64.;
+
+pub fn get_mouse_tile(viewport: Gd<Viewport>) -> Vector2i {
+    let pos = viewport
+        .get_camera_2d()
+        .expect("no valid camera2d")
+        .get_global_mouse_position();
+    tile_at(pos)
+}
+
+pub fn tile_at(pos: Vector2) -> Vector2i {
+    (pos / TILE_SIZE).floor().cast_int()
+}
\ No newline at end of file
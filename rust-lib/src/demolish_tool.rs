@@ -0,0 +1,141 @@
+use godot::builtin::Callable;
+use godot::builtin::Vector2i;
+use godot::classes::BaseButton;
+use godot::classes::INode;
+use godot::classes::Input;
+use godot::classes::Node;
+use godot::classes::TileMapLayer;
+use godot::obj::Base;
+use godot::obj::Gd;
+use godot::obj::WithBaseField;
+use godot::prelude::godot_api;
+use godot::prelude::GodotClass;
+
+use crate::cell_patterns::EnergyReason;
+use crate::cell_patterns::PlayerEnergy;
+use crate::defense_layer::get_mouse_tile;
+use crate::defense_layer::DefenseLayer;
+use crate::ingame_state_tracker::GameplayState;
+use crate::ingame_state_tracker::IngameStateTracker;
+use crate::CellRules;
+
+//lets the player clear live cells while defending, cells they paid for give part of what they paid back
+#[derive(GodotClass)]
+#[class(base = Node, init)]
+pub struct DemolishTool{
+    base: Base<Node>,
+    #[export]
+    target: Option<Gd<TileMapLayer>>,
+    //should be its own layer, CellPattern clears its preview layers while this tool is active
+    #[export]
+    preview: Option<Gd<TileMapLayer>>,
+    #[export]
+    energy_source: Option<Gd<PlayerEnergy>>,
+    #[export]
+    game_state: Option<Gd<IngameStateTracker>>,
+    #[export]
+    toggle_button: Option<Gd<BaseButton>>,
+    //cells up to this many tiles away from the cursor get demolished, 0 for a single tile
+    #[export]
+    brush_radius: i32,
+    #[export]
+    refund_fraction: f32,
+    active: bool
+}
+
+#[godot_api]
+impl INode for DemolishTool {
+    fn ready(&mut self){
+        if let Some(mut button) = self.get_toggle_button(){
+            button.connect("pressed".into(), Callable::from_object_method(&self.to_gd(), "toggle"));
+        }
+    }
+    fn process(&mut self, _delta: f64){
+        let is_defending = self.get_game_state().expect("no game state ref was set").bind().get_state() == GameplayState::DEFENDING;
+        if let Some(mut button) = self.get_toggle_button(){
+            button.set_visible(is_defending);
+        }
+        if !is_defending{
+            if self.active{
+                self.toggle();
+            }
+            return;
+        }
+        if Input::singleton().is_action_just_pressed("toggle_demolish".into()){
+            self.toggle();
+        }
+        if !self.active{
+            return;
+        }
+        let mouse_tile = get_mouse_tile(self.base().get_viewport().expect("no valid viewport"));
+        self.update_hover(mouse_tile);
+        if Input::singleton().is_action_just_pressed("place_pattern".into()){
+            self.demolish(mouse_tile);
+        }
+    }
+}
+
+#[godot_api]
+impl DemolishTool{
+    #[func]
+    pub fn toggle(&mut self){
+        self.active = !self.active;
+        self.get_preview().unwrap().clear();
+    }
+    #[func]
+    pub fn get_refund_at(&self, center: Vector2i) -> i32{
+        let Some(layer) = self.get_defense_layer() else{
+            return 0;
+        };
+        let paid: f32 = self.demolished_cells(center).iter().map(|(pos, _)| layer.bind().paid_for(*pos)).sum();
+        (paid * self.refund_fraction).floor() as i32
+    }
+    #[func]
+    pub fn demolish(&mut self, center: Vector2i){
+        let refund = self.get_refund_at(center);
+        let mut target = self.get_target().unwrap();
+        let mut layer = self.get_defense_layer();
+        for (pos, _) in self.demolished_cells(center){
+            target.set_cell_ex(pos).source_id(0).atlas_coords(CellRules::Empty.to_atlas_coords()).done();
+            if let Some(layer) = layer.as_mut(){
+                layer.bind_mut().forget_paid(pos);
+            }
+        }
+        if refund > 0{
+            self.get_energy_source().unwrap().bind_mut().gain_energy(refund, EnergyReason::Refund);
+        }
+        self.get_preview().unwrap().clear();
+    }
+}
+
+impl DemolishTool{
+    pub fn is_active(&self) -> bool{
+        self.active
+    }
+    //keeps track of which cells were paid for
+    fn get_defense_layer(&self) -> Option<Gd<DefenseLayer>>{
+        self.get_target().and_then(|target| target.try_cast().ok())
+    }
+    fn update_hover(&self, center: Vector2i){
+        let mut preview = self.get_preview().unwrap();
+        preview.clear();
+        for (pos, cell_rules) in self.demolished_cells(center){
+            preview.set_cell_ex(pos).source_id(0).atlas_coords(cell_rules.to_atlas_coords()).done();
+        }
+    }
+    //live player cells within the brush around center
+    fn demolished_cells(&self, center: Vector2i) -> Vec<(Vector2i, CellRules)>{
+        let target = self.get_target().unwrap();
+        let mut cells = vec![];
+        for y in -self.brush_radius..=self.brush_radius{
+            for x in -self.brush_radius..=self.brush_radius{
+                let pos = center + Vector2i::new(x, y);
+                let cell_rules = CellRules::from_tile(target.get_cell_tile_data(pos));
+                if cell_rules == CellRules::BasicFilled{
+                    cells.push((pos, cell_rules));
+                }
+            }
+        }
+        cells
+    }
+}
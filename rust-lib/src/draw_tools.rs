@@ -0,0 +1,239 @@
+use std::collections::HashSet;
+use std::collections::VecDeque;
+
+use godot::builtin::Rect2i;
+use godot::builtin::Vector2i;
+use godot::prelude::Export;
+use godot::prelude::GodotConvert;
+use godot::prelude::Var;
+
+#[derive(GodotConvert, Var, Export, Default, PartialEq, Eq, Clone, Copy, Debug)]
+#[godot(via = i64)]
+pub enum DrawTool {
+    #[default]
+    Brush = 0,
+    Line = 1,
+    Rectangle = 2,
+    FilledRectangle = 3,
+    FloodFill = 4,
+    Eraser = 5,
+}
+
+impl DrawTool {
+    pub fn next(&self) -> Self{
+        match self{
+            Self::Brush => Self::Line,
+            Self::Line => Self::Rectangle,
+            Self::Rectangle => Self::FilledRectangle,
+            Self::FilledRectangle => Self::FloodFill,
+            Self::FloodFill => Self::Eraser,
+            Self::Eraser => Self::Brush
+        }
+    }
+    pub fn index(&self) -> u32{
+        *self as u32
+    }
+    //tools that are dragged out from an anchor and only drawn on release
+    pub fn is_shape(&self) -> bool{
+        match self{
+            Self::Line | Self::Rectangle | Self::FilledRectangle => true,
+            _default => false
+        }
+    }
+    pub fn shape(&self, from: Vector2i, to: Vector2i) -> Vec<Vector2i>{
+        match self{
+            Self::Line => line(from, to),
+            Self::Rectangle => rectangle(from, to, false),
+            Self::FilledRectangle => rectangle(from, to, true),
+            _default => vec![to]
+        }
+    }
+}
+
+#[derive(GodotConvert, Var, Export, Default, PartialEq, Eq, Clone, Copy, Debug)]
+#[godot(via = i64)]
+pub enum Symmetry {
+    #[default]
+    None = 0,
+    TwoWay = 1,
+    FourWay = 2,
+    EightWay = 3,
+}
+
+impl Symmetry {
+    pub fn next(&self) -> Self{
+        match self{
+            Self::None => Self::TwoWay,
+            Self::TwoWay => Self::FourWay,
+            Self::FourWay => Self::EightWay,
+            Self::EightWay => Self::None
+        }
+    }
+    pub fn index(&self) -> u32{
+        *self as u32
+    }
+    //adds the mirrored copies of cells, center2 is the mirror center in half tile units
+    pub fn mirror(&self, cells: Vec<Vector2i>, center2: Vector2i) -> Vec<Vector2i>{
+        let reflect_x = |p: Vector2i| Vector2i::new(center2.x - p.x, p.y);
+        let reflect_y = |p: Vector2i| Vector2i::new(p.x, center2.y - p.y);
+        let swap_axes = |p: Vector2i| Vector2i::new(
+            (center2.x + 2 * p.y - center2.y).div_euclid(2),
+            (center2.y + 2 * p.x - center2.x).div_euclid(2)
+        );
+
+        let mut out: Vec<Vector2i> = Vec::new();
+        let mut seen = HashSet::new();
+        let mut push = |p: Vector2i|{
+            if seen.insert(p){
+                out.push(p);
+            }
+        };
+        for p in cells{
+            push(p);
+            if *self == Self::None{
+                continue;
+            }
+            push(reflect_x(p));
+            if *self == Self::TwoWay{
+                continue;
+            }
+            push(reflect_y(p));
+            push(reflect_x(reflect_y(p)));
+            if *self == Self::FourWay{
+                continue;
+            }
+            let s = swap_axes(p);
+            push(s);
+            push(reflect_x(s));
+            push(reflect_y(s));
+            push(reflect_x(reflect_y(s)));
+        }
+        out
+    }
+}
+
+//center of rect in half tile units, for Symmetry::mirror
+pub fn rect_center2(rect: Rect2i) -> Vector2i{
+    rect.position * 2 + rect.size - Vector2i::new(1, 1)
+}
+
+pub fn rect_contains(rect: Rect2i, p: Vector2i) -> bool{
+    p.x >= rect.position.x && p.y >= rect.position.y
+        && p.x < rect.position.x + rect.size.x && p.y < rect.position.y + rect.size.y
+}
+
+pub fn line(from: Vector2i, to: Vector2i) -> Vec<Vector2i>{
+    let dx = (to.x - from.x).abs();
+    let dy = -(to.y - from.y).abs();
+    let sx = if from.x < to.x { 1 } else { -1 };
+    let sy = if from.y < to.y { 1 } else { -1 };
+    let mut err = dx + dy;
+    let mut p = from;
+    let mut out = vec![p];
+    while p != to{
+        let e2 = 2 * err;
+        if e2 >= dy{
+            err += dy;
+            p.x += sx;
+        }
+        if e2 <= dx{
+            err += dx;
+            p.y += sy;
+        }
+        out.push(p);
+    }
+    out
+}
+
+pub fn rectangle(from: Vector2i, to: Vector2i, filled: bool) -> Vec<Vector2i>{
+    let min = Vector2i::new(from.x.min(to.x), from.y.min(to.y));
+    let max = Vector2i::new(from.x.max(to.x), from.y.max(to.y));
+    let mut out = vec![];
+    for y in min.y..=max.y{
+        for x in min.x..=max.x{
+            let edge = x == min.x || x == max.x || y == min.y || y == max.y;
+            if filled || edge{
+                out.push(Vector2i::new(x, y));
+            }
+        }
+    }
+    out
+}
+
+//4-connected region around start within region where matches is true
+pub fn flood_fill(start: Vector2i, region: Rect2i, matches: impl Fn(Vector2i) -> bool) -> Vec<Vector2i>{
+    let mut out = vec![];
+    if !rect_contains(region, start) || !matches(start){
+        return out;
+    }
+    let mut queue = VecDeque::from([start]);
+    let mut seen = HashSet::from([start]);
+    out.push(start);
+    while let Some(p) = queue.pop_front(){
+        for d in [Vector2i::new(1, 0), Vector2i::new(-1, 0), Vector2i::new(0, 1), Vector2i::new(0, -1)]{
+            let n = p + d;
+            if rect_contains(region, n) && !seen.contains(&n) && matches(n){
+                seen.insert(n);
+                out.push(n);
+                queue.push_back(n);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::test_helpers::p;
+
+    #[test]
+    fn line_includes_both_ends(){
+        assert_eq!(line(p(0, 0), p(3, 0)), vec![p(0, 0), p(1, 0), p(2, 0), p(3, 0)]);
+        assert_eq!(line(p(0, 0), p(2, 2)), vec![p(0, 0), p(1, 1), p(2, 2)]);
+        assert_eq!(line(p(1, 1), p(1, 1)), vec![p(1, 1)]);
+    }
+
+    #[test]
+    fn rectangle_outline_and_filled(){
+        assert_eq!(rectangle(p(2, 2), p(0, 0), false).len(), 8);
+        assert_eq!(rectangle(p(0, 0), p(2, 2), true).len(), 9);
+        assert!(!rectangle(p(0, 0), p(2, 2), false).contains(&p(1, 1)));
+    }
+
+    #[test]
+    fn flood_fill_stays_in_region_and_matches(){
+        let region = Rect2i::new(p(0, 0), p(4, 4));
+        let wall = |q: Vector2i| q.x == 2;
+        let filled = flood_fill(p(0, 0), region, |q| !wall(q));
+        assert_eq!(filled.len(), 8);
+        assert!(filled.iter().all(|q| q.x < 2));
+        assert!(flood_fill(p(5, 5), region, |_| true).is_empty());
+    }
+
+    #[test]
+    fn mirror_around_whole_tile_center(){
+        let center2 = rect_center2(Rect2i::new(p(0, 0), p(5, 5)));
+        assert_eq!(center2, p(4, 4));
+        assert_eq!(Symmetry::TwoWay.mirror(vec![p(0, 1)], center2), vec![p(0, 1), p(4, 1)]);
+        assert_eq!(Symmetry::TwoWay.mirror(vec![p(2, 1)], center2), vec![p(2, 1)]);
+    }
+
+    #[test]
+    fn mirror_around_half_tile_center(){
+        let center2 = rect_center2(Rect2i::new(p(0, 0), p(4, 4)));
+        assert_eq!(center2, p(3, 3));
+        assert_eq!(Symmetry::TwoWay.mirror(vec![p(1, 0)], center2), vec![p(1, 0), p(2, 0)]);
+        let four_way = Symmetry::FourWay.mirror(vec![p(0, 0)], center2);
+        assert_eq!(four_way, vec![p(0, 0), p(3, 0), p(0, 3), p(3, 3)]);
+    }
+
+    #[test]
+    fn eight_way_mirror_covers_the_diagonals(){
+        let center2 = rect_center2(Rect2i::new(p(0, 0), p(5, 5)));
+        let cells = Symmetry::EightWay.mirror(vec![p(1, 0)], center2);
+        assert_eq!(cells.len(), 8);
+        assert!(cells.contains(&p(0, 1)));
+        assert!(cells.contains(&p(4, 3)));
+    }
+}
@@ -0,0 +1,137 @@
+use std::mem;
+
+use godot::builtin::Vector2i;
+
+use crate::CellRules;
+
+pub struct CellEdit{
+    pos: Vector2i,
+    before: CellRules,
+    after: CellRules
+}
+
+//undo/redo stacks of strokes, a stroke being every cell changed between pressing and releasing the brush
+#[derive(Default)]
+pub struct EditHistory{
+    undo_stack: Vec<Vec<CellEdit>>,
+    redo_stack: Vec<Vec<CellEdit>>,
+    stroke: Vec<CellEdit>
+}
+
+impl EditHistory{
+    pub fn record(&mut self, pos: Vector2i, before: CellRules, after: CellRules){
+        if let Some(edit) = self.stroke.iter_mut().find(|e| e.pos == pos){
+            edit.after = after;
+            return;
+        }
+        if before == after{
+            return;
+        }
+        self.stroke.push(CellEdit { pos, before, after });
+    }
+    pub fn end_stroke(&mut self){
+        //cells painted back to what they were before the stroke
+        self.stroke.retain(|edit| edit.before != edit.after);
+        if self.stroke.is_empty(){
+            return;
+        }
+        self.undo_stack.push(mem::take(&mut self.stroke));
+        self.redo_stack.clear();
+    }
+    //returns the cells to write to take back the last stroke
+    pub fn undo(&mut self) -> Vec<(Vector2i, CellRules)>{
+        self.end_stroke();
+        let Some(stroke) = self.undo_stack.pop() else{
+            return vec![];
+        };
+        let cells = stroke.iter().rev().map(|e| (e.pos, e.before.clone())).collect();
+        self.redo_stack.push(stroke);
+        cells
+    }
+    //returns the cells to write to reapply the last undone stroke
+    pub fn redo(&mut self) -> Vec<(Vector2i, CellRules)>{
+        self.end_stroke();
+        let Some(stroke) = self.redo_stack.pop() else{
+            return vec![];
+        };
+        let cells = stroke.iter().map(|e| (e.pos, e.after.clone())).collect();
+        self.undo_stack.push(stroke);
+        cells
+    }
+    pub fn clear(&mut self){
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.stroke.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::test_helpers::p;
+
+    #[test]
+    fn undo_takes_back_a_whole_stroke(){
+        let mut history = EditHistory::default();
+        history.record(p(0, 0), CellRules::Empty, CellRules::BasicFilled);
+        history.record(p(1, 0), CellRules::Empty, CellRules::BasicFilled);
+        history.end_stroke();
+        assert_eq!(history.undo(), vec![(p(1, 0), CellRules::Empty), (p(0, 0), CellRules::Empty)]);
+        assert_eq!(history.undo(), vec![]);
+    }
+
+    #[test]
+    fn redo_reapplies_undone_stroke(){
+        let mut history = EditHistory::default();
+        history.record(p(0, 0), CellRules::Empty, CellRules::BasicFilled);
+        history.end_stroke();
+        history.undo();
+        assert_eq!(history.redo(), vec![(p(0, 0), CellRules::BasicFilled)]);
+        assert_eq!(history.redo(), vec![]);
+    }
+
+    #[test]
+    fn repainting_a_cell_keeps_its_first_before(){
+        let mut history = EditHistory::default();
+        history.record(p(0, 0), CellRules::Empty, CellRules::BasicFilled);
+        history.record(p(0, 0), CellRules::BasicFilled, CellRules::PermaCell);
+        history.end_stroke();
+        assert_eq!(history.undo(), vec![(p(0, 0), CellRules::Empty)]);
+        assert_eq!(history.redo(), vec![(p(0, 0), CellRules::PermaCell)]);
+    }
+
+    #[test]
+    fn unchanged_cells_make_no_stroke(){
+        let mut history = EditHistory::default();
+        history.record(p(0, 0), CellRules::Empty, CellRules::Empty);
+        history.end_stroke();
+        assert_eq!(history.undo(), vec![]);
+    }
+
+    #[test]
+    fn cells_painted_back_make_no_stroke(){
+        let mut history = EditHistory::default();
+        history.record(p(0, 0), CellRules::Empty, CellRules::BasicFilled);
+        history.record(p(0, 0), CellRules::BasicFilled, CellRules::Empty);
+        history.end_stroke();
+        assert_eq!(history.undo(), vec![]);
+    }
+
+    #[test]
+    fn new_stroke_clears_redo(){
+        let mut history = EditHistory::default();
+        history.record(p(0, 0), CellRules::Empty, CellRules::BasicFilled);
+        history.end_stroke();
+        history.undo();
+        history.record(p(1, 1), CellRules::Empty, CellRules::BasicFilled);
+        history.end_stroke();
+        assert_eq!(history.redo(), vec![]);
+    }
+
+    #[test]
+    fn undo_ends_the_stroke_in_progress(){
+        let mut history = EditHistory::default();
+        history.record(p(2, 2), CellRules::Empty, CellRules::BasicFilled);
+        assert_eq!(history.undo(), vec![(p(2, 2), CellRules::Empty)]);
+    }
+}
@@ -0,0 +1,203 @@
+use godot::builtin::Variant;
+use godot::classes::INode;
+use godot::classes::INode2D;
+use godot::classes::Node;
+use godot::classes::Node2D;
+use godot::classes::PackedScene;
+use godot::classes::PathFollow2D;
+use godot::obj::Base;
+use godot::obj::Gd;
+use godot::obj::Inherits;
+use godot::obj::WithBaseField;
+use godot::prelude::godot_api;
+use godot::prelude::GodotClass;
+
+use crate::defense_layer;
+use crate::defense_layer::DefenseLayer;
+use crate::flow_field::GridEnemy;
+use crate::Damageable;
+
+//every enemy in the level, a wave isn't over until this group is empty
+pub const ENEMY_GROUP: &str = "enemies";
+
+//first child of enemy of type T, enemy behaviours are added as child nodes next to the Damageable
+pub fn find_component<T: GodotClass + Inherits<Node>>(enemy: &Gd<Node>) -> Option<Gd<T>>{
+    enemy.get_children().iter_shared().find_map(|child| child.try_cast::<T>().ok())
+}
+
+//takes a flat amount off of every hit
+#[derive(GodotClass)]
+#[class(base = Node, init)]
+pub struct Armor{
+    base: Base<Node>,
+    #[export]
+    armor: i32,
+    //hits never do less than this, so armor can't make an enemy immune
+    #[export]
+    min_damage: i32
+}
+
+impl Armor{
+    pub fn reduce(&self, amount: i32) -> i32{
+        (amount - self.armor).max(self.min_damage.min(amount))
+    }
+}
+
+//soaks up damage before health does and refills after a while without being hit
+#[derive(GodotClass)]
+#[class(base = Node, init)]
+pub struct Shield{
+    base: Base<Node>,
+    #[export]
+    max_shield: i32,
+    #[export]
+    shield: i32,
+    #[export]
+    regen_per_second: f32,
+    //seconds after a hit before the shield starts refilling
+    #[export]
+    regen_delay: f64,
+    since_hit: f64,
+    regen_progress: f32
+}
+
+#[godot_api]
+impl INode for Shield{
+    fn physics_process(&mut self, delta: f64){
+        self.since_hit += delta;
+        if self.since_hit < self.regen_delay || self.shield >= self.max_shield{
+            return;
+        }
+        self.regen_progress += self.regen_per_second * delta as f32;
+        let regen = self.regen_progress.floor();
+        self.regen_progress -= regen;
+        self.shield = (self.shield + regen as i32).min(self.max_shield);
+    }
+}
+
+impl Shield{
+    //returns the damage left over for health
+    pub fn absorb(&mut self, amount: i32) -> i32{
+        self.since_hit = 0.;
+        self.regen_progress = 0.;
+        let absorbed = amount.min(self.shield);
+        self.shield -= absorbed;
+        amount - absorbed
+    }
+}
+
+//sends smaller enemies down the path from where this enemy died
+#[derive(GodotClass)]
+#[class(base = Node, init)]
+pub struct Splitter{
+    base: Base<Node>,
+    #[export]
+    child_scene: Option<Gd<PackedScene>>,
+    #[export]
+    count: i32,
+    //distance along the path between each child
+    #[export]
+    spacing: f32
+}
+
+impl Splitter{
+    //called on death, the children are added deferred since this usually runs inside a physics callback
+    pub fn split(&self){
+        let Some(scene) = self.get_child_scene() else{
+            return;
+        };
+        let Some(enemy) = self.base().get_parent() else{
+            return;
+        };
+        let Some(mut path) = enemy.get_parent() else{
+            return;
+        };
+        let progress = enemy.clone().try_cast::<PathFollow2D>().ok().map(|enemy| enemy.get_progress());
+        let position = enemy.clone().try_cast::<Node2D>().ok().map(|enemy| enemy.get_position());
+        for i in 0..self.count{
+            let child = scene.instantiate().expect("failed to create enemy");
+            if let (Some(progress), Ok(mut follow)) = (progress, child.clone().try_cast::<PathFollow2D>()){
+                follow.set_progress((progress - i as f32 * self.spacing).max(0.));
+            }else if let (Some(position), Ok(mut child)) = (position, child.clone().try_cast::<Node2D>()){
+                //enemies off of a path, like GridEnemy, start where this one died
+                child.set_position(position);
+            }
+            if let Ok(mut grid_enemy) = child.clone().try_cast::<GridEnemy>(){
+                grid_enemy.bind_mut().set_start_on_spawn_tile(false);
+            }
+            path.call_deferred("add_child".into(), &[Variant::from(child)]);
+        }
+    }
+}
+
+//heals every enemy on the same path within radius
+#[derive(GodotClass)]
+#[class(base = Node2D, init)]
+pub struct Healer{
+    base: Base<Node2D>,
+    #[export]
+    radius: f32,
+    #[export]
+    heal_amount: i32,
+    #[export]
+    interval: f64,
+    timer: f64
+}
+
+#[godot_api]
+impl INode2D for Healer{
+    fn physics_process(&mut self, delta: f64){
+        self.timer += delta;
+        if self.timer < self.interval{
+            return;
+        }
+        self.timer = 0.;
+        let Some(path) = self.base().get_parent().and_then(|enemy| enemy.get_parent()) else{
+            return;
+        };
+        let position = self.base().get_global_position();
+        for enemy in path.get_children().iter_shared(){
+            let Ok(enemy) = enemy.try_cast::<Node2D>() else{
+                continue;
+            };
+            if enemy.get_global_position().distance_to(position) > self.radius{
+                continue;
+            }
+            if let Some(mut damageable) = find_component::<Damageable>(&enemy.upcast()){
+                damageable.bind_mut().heal(self.heal_amount);
+            }
+        }
+    }
+}
+
+//kills the nearest live cell within range every interval seconds
+#[derive(GodotClass)]
+#[class(base = Node2D, init)]
+pub struct Sapper{
+    base: Base<Node2D>,
+    //in tiles
+    #[export]
+    range: i32,
+    #[export]
+    interval: f64,
+    timer: f64
+}
+
+#[godot_api]
+impl INode2D for Sapper{
+    fn physics_process(&mut self, delta: f64){
+        self.timer += delta;
+        if self.timer < self.interval{
+            return;
+        }
+        let Some(mut layer) = DefenseLayer::find(self.base().get_tree().unwrap()) else{
+            return;
+        };
+        let center = defense_layer::tile_at(self.base().get_global_position());
+        let Some(tile) = layer.bind().nearest_killable_cell(center, self.range) else{
+            return;
+        };
+        self.timer = 0.;
+        layer.bind_mut().kill_cell(tile);
+    }
+}
@@ -1,6 +1,8 @@
-use godot::{builtin::{Array, Callable}, classes::{Area2D, IArea2D, INode, Node, PackedScene, Path2D, TextureProgressBar}, obj::{Base, Gd, WithBaseField}, prelude::{godot_api, GodotClass}};
+use std::collections::HashSet;
 
-use crate::{ingame_state_tracker::{GameplayState, IngameStateTracker}, player_health::PlayerHealth};
+use godot::{builtin::{Array, Callable, PackedFloat32Array, Vector2i}, classes::{Area2D, IArea2D, INode, Node, PackedScene, Path2D, TextureProgressBar, TileMapLayer}, global::randf, obj::{Base, Gd, WithBaseField}, prelude::{godot_api, Export, GodotClass, GodotConvert, Var}};
+
+use crate::{cell_patterns::{EnergyReason, PlayerEnergy}, enemy_components::ENEMY_GROUP, ingame_state_tracker::{GameplayState, IngameStateTracker}, player_health::PlayerHealth, wave_spawner::WaveSpawner, CellRules};
 
 #[derive(GodotClass)]
 #[class(base = Node, init)]
@@ -18,7 +20,10 @@ pub struct EnemySpawner{
     #[export]
     spawns_left: u32,
     #[export]
-    game_state: Option<Gd<IngameStateTracker>>
+    game_state: Option<Gd<IngameStateTracker>>,
+    //energy given to the player once this spawner has sent out all of its enemies
+    #[export]
+    completion_bonus: i32
 }
 
 #[godot_api]
@@ -54,6 +59,7 @@ impl INode for EnemySpawner{
             parent.add_child(instance);
             self.spawns_left -= 1;
             if self.is_done(){
+                self.pay_completion_bonus();
                 self.base_mut().emit_signal("spawning_end".into(), &[]);
             }
         }
@@ -74,6 +80,25 @@ impl EnemySpawner{
     fn is_done(&self) -> bool{
         self.spawns_left <= 0
     }
+    fn pay_completion_bonus(&self){
+        if self.completion_bonus <= 0{
+            return;
+        }
+        if let Some(mut energy) = PlayerEnergy::find(self.base().get_tree().unwrap()){
+            energy.bind_mut().gain_energy(self.completion_bonus, EnergyReason::WaveBonus);
+        }
+    }
+}
+
+//how an enemy at the end of a path picks which branch to follow
+#[derive(GodotConvert, Var, Export, Default, PartialEq, Eq, Clone, Copy, Debug)]
+#[godot(via = i64)]
+pub enum BranchChoice{
+    //random, weighted by branch_weights
+    #[default]
+    Weighted = 0,
+    //the branch with the fewest live cells within defense_radius tiles of it
+    LeastDefended = 1,
 }
 
 #[derive(GodotClass)]
@@ -81,13 +106,73 @@ impl EnemySpawner{
 pub struct EnemyPath{
     base: Base<Path2D>,
     #[export]
-    player_health: Option<Gd<PlayerHealth>>
+    player_health: Option<Gd<PlayerHealth>>,
+    //paths enemies move onto when they reach the end of this one, each should start where this one ends,
+    //enemies reaching the end of a path without branches hit the player
+    #[export]
+    branches: Array<Gd<EnemyPath>>,
+    //one per branch, missing weights count as 1
+    #[export]
+    branch_weights: PackedFloat32Array,
+    #[export]
+    branch_choice: BranchChoice,
+    //layer counted for live cells with LeastDefended
+    #[export]
+    defense_layer: Option<Gd<TileMapLayer>>,
+    #[export]
+    defense_radius: i32
 }
 
 impl EnemyPath{
     pub fn hit_player(&self){
         self.get_player_health().unwrap().bind_mut().take_damage(1);
     }
+    pub fn get_length(&self) -> f32{
+        self.base().get_curve().map_or(0., |curve| curve.get_baked_length())
+    }
+    //where an enemy at the end of this path goes next, None when it has reached the player
+    pub fn choose_branch(&self) -> Option<Gd<EnemyPath>>{
+        if self.branches.is_empty(){
+            return None;
+        }
+        match self.branch_choice{
+            BranchChoice::Weighted => {
+                let weights: Vec<f32> = (0..self.branches.len())
+                    .map(|i| self.branch_weights.get(i).unwrap_or(1.).max(0.))
+                    .collect();
+                let total: f32 = weights.iter().sum();
+                let mut roll = randf() as f32 * total;
+                for (i, weight) in weights.iter().enumerate(){
+                    if roll < *weight{
+                        return self.branches.get(i);
+                    }
+                    roll -= weight;
+                }
+                self.branches.get(self.branches.len() - 1)
+            }
+            BranchChoice::LeastDefended => {
+                self.branches.iter_shared().min_by_key(|branch| self.count_defenses(branch))
+            }
+        }
+    }
+    //live cells within defense_radius tiles of the path
+    fn count_defenses(&self, path: &Gd<EnemyPath>) -> usize{
+        let (Some(layer), Some(curve)) = (self.get_defense_layer(), path.get_curve()) else{
+            return 0;
+        };
+        let mut near_tiles = HashSet::new();
+        for point in curve.get_baked_points().as_slice(){
+            let tile = layer.local_to_map(layer.to_local(path.to_global(*point)));
+            for y in -self.defense_radius..=self.defense_radius{
+                for x in -self.defense_radius..=self.defense_radius{
+                    near_tiles.insert((tile.x + x, tile.y + y));
+                }
+            }
+        }
+        near_tiles.into_iter()
+            .filter(|(x, y)| CellRules::from_tile(layer.get_cell_tile_data(Vector2i::new(*x, *y))).is_alive())
+            .count()
+    }
 }
 
 #[derive(GodotClass)]
@@ -97,6 +182,9 @@ struct EnemySpawnerProgressTracker{
     progress_bar: Option<Gd<TextureProgressBar>>,
     #[export]
     spawners: Array<Gd<EnemySpawner>>,
+    //each group of a wave spawner counts as one step of the progress bar
+    #[export]
+    wave_spawners: Array<Gd<WaveSpawner>>,
     base: Base<Area2D>,
     #[export]
     gamestate: Option<Gd<IngameStateTracker>>,
@@ -107,24 +195,42 @@ struct EnemySpawnerProgressTracker{
 #[godot_api]
 impl IArea2D for EnemySpawnerProgressTracker{
     fn ready(&mut self){
-        self.get_progress_bar().unwrap().set_max(self.spawners.len() as f64);
+        let group_count: i32 = self.wave_spawners.iter_shared().map(|spawner| spawner.bind().get_group_count()).sum();
+        let total = self.spawners.len() + group_count as usize;
+        self.get_progress_bar().unwrap().set_max(total as f64);
     }
     fn physics_process(&mut self, _delta: f64) {
-        
+        let mut gamestate = self.get_gamestate().unwrap();
+        if gamestate.bind().get_state() != GameplayState::DEFENDING{
+            return;
+        }
+        let wave = gamestate.bind().get_wave();
+
         let mut active_spawners_count = self.spawners.len();
         for spawner in self.spawners.iter_shared() {
             if spawner.bind().is_done(){
                 active_spawners_count -= 1;
             }
         }
+        for spawner in self.wave_spawners.iter_shared() {
+            let spawner = spawner.bind();
+            active_spawners_count += (spawner.get_group_count() - spawner.get_finished_group_count()) as usize;
+        }
 
         self.get_progress_bar().unwrap().set_value(active_spawners_count as f64);
 
-        if active_spawners_count == 0 && self.base().get_overlapping_areas().len() == 0{
-            if self.get_gamestate().unwrap().bind().get_state() != GameplayState::SUCCESS{
+        //plain EnemySpawners all belong to the first wave
+        let wave_spawned = self.spawners.iter_shared().all(|spawner| spawner.bind().is_done())
+            && self.wave_spawners.iter_shared().all(|spawner| spawner.bind().is_wave_done(wave));
+        //enemies join the group as soon as they're added, so one spawned this frame is already counted
+        let enemies_left = self.base().get_tree().unwrap().get_nodes_in_group(ENEMY_GROUP.into()).len();
+        if wave_spawned && enemies_left == 0{
+            let wave_count = self.wave_spawners.iter_shared().map(|spawner| spawner.bind().get_wave_count()).max().unwrap_or(0).max(1);
+            let was_last = wave + 1 >= wave_count;
+            if was_last{
                 self.base().get_tree().unwrap().change_scene_to_packed(self.get_win_screen().unwrap());
             }
-            self.get_gamestate().unwrap().bind_mut().win();
+            gamestate.bind_mut().clear_wave(was_last);
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,239 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use godot::builtin::Array;
+use godot::builtin::Callable;
+use godot::builtin::Rect2i;
+use godot::builtin::Vector2;
+use godot::builtin::Vector2i;
+use godot::classes::INode2D;
+use godot::classes::Node2D;
+use godot::classes::TileMapLayer;
+use godot::global::randi_range;
+use godot::obj::Base;
+use godot::obj::Gd;
+use godot::obj::WithBaseField;
+use godot::prelude::godot_api;
+use godot::prelude::GodotClass;
+
+use crate::cell_grid::CellGrid;
+use crate::defense_layer::CELLS_UPDATED_SIGNAL;
+use crate::draw_tools;
+use crate::enemy_components::find_component;
+use crate::enemy_components::ENEMY_GROUP;
+use crate::player_health::PlayerHealth;
+use crate::status_effects::StatusEffects;
+use crate::CellRules;
+
+const UNREACHABLE: i32 = i32::MAX;
+//costs are in tenths of a tile so diagonal steps can cost 1.4 tiles
+const STRAIGHT_COST: i32 = 10;
+const DIAGONAL_COST: i32 = 14;
+const NEIGHBORS: [(i32, i32); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+//distance to the nearest goal for every tile in rect, recomputed whenever the defense layer steps,
+//GridEnemy children follow it downhill instead of a fixed path
+#[derive(GodotClass)]
+#[class(base = Node2D, init)]
+pub struct FlowField{
+    base: Base<Node2D>,
+    #[export]
+    defense_layer: Option<Gd<TileMapLayer>>,
+    #[export]
+    rect: Rect2i,
+    #[export]
+    goal_tiles: Array<Vector2i>,
+    //enemies added by a spawner start on one of these
+    #[export]
+    spawn_tiles: Array<Vector2i>,
+    //cost of walking through a live cell in tiles, 0 to make live cells walls,
+    //enemies walled off from every goal still break through the cheapest wall instead of stopping
+    #[export]
+    live_cell_cost: i32,
+    #[export]
+    player_health: Option<Gd<PlayerHealth>>,
+    distances: Vec<i32>
+}
+
+#[godot_api]
+impl INode2D for FlowField{
+    fn ready(&mut self){
+        let mut layer = self.get_defense_layer().expect("no defense layer set");
+        layer.connect(CELLS_UPDATED_SIGNAL.into(), Callable::from_object_method(&self.to_gd(), "recompute"));
+        self.recompute();
+    }
+}
+
+#[godot_api]
+impl FlowField{
+    //dijkstra outward from the goals
+    #[func]
+    pub fn recompute(&mut self){
+        let layer = self.get_defense_layer().expect("no defense layer set");
+        let grid = CellGrid::new(self.rect, |tile| CellRules::from_tile(layer.get_cell_tile_data(tile)));
+        self.distances = vec![UNREACHABLE; (self.rect.size.x * self.rect.size.y).max(0) as usize];
+
+        let mut queue = BinaryHeap::new();
+        for goal in self.goal_tiles.iter_shared(){
+            if let Some(index) = self.index_of(goal){
+                self.distances[index] = 0;
+                queue.push(Reverse((0, goal.x, goal.y)));
+            }
+        }
+        while let Some(Reverse((distance, x, y))) = queue.pop(){
+            let tile = Vector2i::new(x, y);
+            if distance > self.distances[self.index_of(tile).unwrap()]{
+                continue;
+            }
+            for (dx, dy) in NEIGHBORS{
+                let next = tile + Vector2i::new(dx, dy);
+                let Some(index) = self.index_of(next) else{
+                    continue;
+                };
+                let Some(cost) = self.step_cost(&grid, tile, next) else{
+                    continue;
+                };
+                if distance + cost < self.distances[index]{
+                    self.distances[index] = distance + cost;
+                    queue.push(Reverse((distance + cost, next.x, next.y)));
+                }
+            }
+        }
+    }
+    #[func]
+    pub fn get_distance(&self, tile: Vector2i) -> i32{
+        self.index_of(tile).map_or(UNREACHABLE, |index| self.distances[index])
+    }
+}
+
+impl FlowField{
+    //neighbor of tile closest to a goal, tile itself when none are closer
+    pub fn next_tile(&self, tile: Vector2i) -> Vector2i{
+        NEIGHBORS.iter()
+            .map(|(dx, dy)| tile + Vector2i::new(*dx, *dy))
+            .filter(|next| self.get_distance(*next) != UNREACHABLE)
+            .min_by_key(|next| self.get_distance(*next))
+            .filter(|next| self.get_distance(*next) < self.get_distance(tile))
+            .unwrap_or(tile)
+    }
+    pub fn is_goal(&self, tile: Vector2i) -> bool{
+        self.goal_tiles.contains(&tile)
+    }
+    pub fn hit_player(&self){
+        self.get_player_health().unwrap().bind_mut().take_damage(1);
+    }
+    pub fn tile_of(&self, global_position: Vector2) -> Vector2i{
+        let layer = self.get_defense_layer().unwrap();
+        layer.local_to_map(layer.to_local(global_position))
+    }
+    pub fn tile_center(&self, tile: Vector2i) -> Vector2{
+        let layer = self.get_defense_layer().unwrap();
+        layer.to_global(layer.map_to_local(tile))
+    }
+    //center of the goal tile closest to global_position, None when there are no goals
+    pub fn nearest_goal(&self, global_position: Vector2) -> Option<Vector2>{
+        self.goal_tiles.iter_shared()
+            .map(|tile| self.tile_center(tile))
+            .min_by(|a, b| a.distance_squared_to(global_position).total_cmp(&b.distance_squared_to(global_position)))
+    }
+    pub fn random_spawn_tile(&self) -> Option<Vector2i>{
+        if self.spawn_tiles.is_empty(){
+            return None;
+        }
+        self.spawn_tiles.get(randi_range(0, self.spawn_tiles.len() as i64 - 1) as usize)
+    }
+    fn index_of(&self, tile: Vector2i) -> Option<usize>{
+        if !draw_tools::rect_contains(self.rect, tile){
+            return None;
+        }
+        let local = tile - self.rect.position;
+        Some((local.y * self.rect.size.x + local.x) as usize)
+    }
+    //more than any route around the walls can cost, so a wall is only crossed when there is no way around it
+    fn wall_cost(&self) -> i32{
+        (self.rect.size.x * self.rect.size.y).max(1) * 2 * STRAIGHT_COST
+    }
+    //diagonal steps cost more and can't cut past a wall
+    fn step_cost(&self, grid: &CellGrid, from: Vector2i, to: Vector2i) -> Option<i32>{
+        let is_wall = |tile: Vector2i| self.live_cell_cost <= 0 && grid.get(tile).is_alive();
+        let cost = if !grid.get(to).is_alive(){
+            STRAIGHT_COST
+        }else if self.live_cell_cost > 0{
+            self.live_cell_cost * STRAIGHT_COST
+        }else{
+            self.wall_cost()
+        };
+        if from.x != to.x && from.y != to.y{
+            if is_wall(to) || is_wall(Vector2i::new(from.x, to.y)) || is_wall(Vector2i::new(to.x, from.y)){
+                return None;
+            }
+            return Some(cost * DIAGONAL_COST / STRAIGHT_COST);
+        }
+        Some(cost)
+    }
+}
+
+//enemy that walks the FlowField it's a child of
+#[derive(GodotClass)]
+#[class(base = Node2D, init)]
+pub struct GridEnemy{
+    base: Base<Node2D>,
+    #[export]
+    speed: f32,
+    //moves the enemy onto one of the FlowField's spawn tiles when it's added,
+    //off for enemies placed by hand or split off of another enemy
+    #[export]
+    start_on_spawn_tile: bool
+}
+
+#[godot_api]
+impl INode2D for GridEnemy{
+    fn ready(&mut self){
+        self.base_mut().add_to_group(ENEMY_GROUP.into());
+        if !self.start_on_spawn_tile{
+            return;
+        }
+        let field = self.get_flow_field();
+        if let Some(tile) = field.bind().random_spawn_tile(){
+            let spawn = field.bind().tile_center(tile);
+            self.base_mut().set_global_position(spawn);
+        }
+    }
+    fn physics_process(&mut self, delta: f64){
+        let field = self.get_flow_field();
+        let position = self.base().get_global_position();
+        let tile = field.bind().tile_of(position);
+        if field.bind().is_goal(tile){
+            field.bind().hit_player();
+            self.base_mut().queue_free();
+            return;
+        }
+        let next = field.bind().next_tile(tile);
+        //off the field or with nowhere downhill to go, walk straight at the goal rather than stand around
+        //holding up the wave, and leave if there's no goal at all
+        let target = if next != tile{
+            field.bind().tile_center(next)
+        }else if let Some(goal) = field.bind().nearest_goal(position){
+            goal
+        }else{
+            self.base_mut().queue_free();
+            return;
+        };
+        let speed_multiplier = find_component::<StatusEffects>(&self.to_gd().upcast())
+            .map_or(1., |status_effects| status_effects.bind().speed_multiplier());
+        let step = self.speed * speed_multiplier * delta as f32;
+        let offset = target - position;
+        if offset.length() <= step{
+            self.base_mut().set_global_position(target);
+        }else{
+            self.base_mut().set_global_position(position + offset.normalized() * step);
+            self.base_mut().set_rotation(offset.angle());
+        }
+    }
+}
+
+impl GridEnemy{
+    fn get_flow_field(&self) -> Gd<FlowField>{
+        self.base().get_parent().expect("no parent???").try_cast().expect("GridEnemy is not a child of a FlowField")
+    }
+}
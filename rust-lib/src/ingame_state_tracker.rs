@@ -2,6 +2,9 @@ use std::fmt::Display;
 
 use godot::{builtin::Callable, classes::{BaseButton, INode}, global::{godot_print, godot_warn}, obj::{Base, Gd, WithBaseField}, prelude::{godot_api, GodotClass}};
 use godot::classes::Node;
+use godot::builtin::{Array, GString, PackedStringArray, Variant};
+
+use crate::player_progress::PlayerProgress;
 
 
 
@@ -10,8 +13,17 @@ use godot::classes::Node;
 pub struct IngameStateTracker{
     base: Base<Node>,
     state: GameplayState,
+    //index of the wave being drawn for or defended against
+    wave: i32,
+    #[export]
+    drawing_done_button: Option<Gd<BaseButton>>,
+    //saved to the player's progress when this level is won, along with what it unlocks
+    #[export]
+    level_name: GString,
     #[export]
-    drawing_done_button: Option<Gd<BaseButton>>
+    unlocks_blueprints: PackedStringArray,
+    #[export]
+    unlocks_brush_tiles: Array<u16>
 }
 
 #[godot_api]
@@ -20,7 +32,11 @@ impl INode for IngameStateTracker {
         Self{
             base,
             state: GameplayState::DEFENDING,
-            drawing_done_button: None
+            wave: 0,
+            drawing_done_button: None,
+            level_name: GString::new(),
+            unlocks_blueprints: PackedStringArray::new(),
+            unlocks_brush_tiles: Array::new()
         }
     }
     fn ready(&mut self){
@@ -40,6 +56,10 @@ impl IngameStateTracker{
     fn on_death();
     #[signal]
     fn on_win();
+    #[signal]
+    fn wave_started(index: i32);
+    #[signal]
+    fn wave_cleared(index: i32);
 
     #[func]
     pub fn end_wave(&mut self){
@@ -58,11 +78,34 @@ impl IngameStateTracker{
             self.state = GameplayState::DEFENDING;
             self.get_drawing_done_button().unwrap().set_visible(false);
             self.base_mut().emit_signal(START_WAVE_SIGNAL.into(), &[]);
+            let wave = self.wave;
+            self.base_mut().emit_signal(WAVE_STARTED_SIGNAL.into(), &[Variant::from(wave)]);
             godot_print!("end_drawing");
         }else{
             self.warn_state_change_invalid(GameplayState::DEFENDING);
         }
     }
+    //called once every enemy of the current wave is gone, wins the level after the last wave
+    #[func]
+    pub fn clear_wave(&mut self, was_last: bool){
+        if self.get_state() != GameplayState::DEFENDING{
+            self.warn_state_change_invalid(if was_last { GameplayState::SUCCESS } else { GameplayState::DRAWING });
+            return;
+        }
+        let wave = self.wave;
+        self.base_mut().emit_signal(WAVE_CLEARED_SIGNAL.into(), &[Variant::from(wave)]);
+        godot_print!("wave {} cleared", wave);
+        if was_last{
+            self.win();
+        }else{
+            self.wave += 1;
+            self.end_wave();
+        }
+    }
+    #[func]
+    pub fn get_wave(&self) -> i32{
+        self.wave
+    }
     #[func]
     pub fn die(&mut self){
         if self.get_state() == GameplayState::DEFENDING || self.get_state() == GameplayState::DRAWING{
@@ -78,6 +121,7 @@ impl IngameStateTracker{
     pub fn win(&mut self){
         if self.get_state() == GameplayState::DEFENDING{
             self.state = GameplayState::SUCCESS;
+            self.save_progress();
             self.base_mut().emit_signal(WIN_SIGNAL.into(), &[]);
             godot_print!("you won");
         }else {
@@ -90,6 +134,8 @@ pub const WIN_SIGNAL: &str = "on_win";
 pub const DEATH_SIGNAL: &str = "on_death";
 pub const START_WAVE_SIGNAL: &str = "on_start_wave";
 pub const START_DRAW_SIGNAL: &str = "on_start_draw";
+pub const WAVE_STARTED_SIGNAL: &str = "wave_started";
+pub const WAVE_CLEARED_SIGNAL: &str = "wave_cleared";
 
 impl IngameStateTracker{
     fn warn_state_change_invalid(&self, next: GameplayState){
@@ -98,6 +144,19 @@ impl IngameStateTracker{
     pub fn get_state(&self)->GameplayState{
         self.state
     }
+    fn save_progress(&self){
+        let mut progress = PlayerProgress::load();
+        if !self.level_name.is_empty(){
+            progress.complete_level(self.level_name.clone());
+        }
+        for unlock_id in self.unlocks_blueprints.as_slice(){
+            progress.unlock_blueprint(unlock_id.clone());
+        }
+        for tile in self.unlocks_brush_tiles.iter_shared(){
+            progress.unlock_brush_tile(tile);
+        }
+        progress.save();
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Copy)]
@@ -1,10 +1,21 @@
 use core::f64;
 use core::panic;
 
+use cell_patterns::EnergyReason;
+use cell_patterns::PlayerEnergy;
 use defense_layer::TILE_TYPE_DATA_LAYER;
+use enemy_components::find_component;
+use enemy_components::ENEMY_GROUP;
+use enemy_components::Armor;
+use enemy_components::Shield;
+use enemy_components::Splitter;
+use status_effects::StatusEffect;
+use status_effects::StatusEffects;
+use boss::Boss;
 use enemy_spawner::EnemyPath;
 use godot::builtin::Callable;
 use godot::builtin::GString;
+use godot::builtin::Variant;
 use godot::builtin::Vector2;
 use godot::builtin::Vector2i;
 use godot::classes::Area2D;
@@ -33,7 +44,7 @@ struct MyExtension;
 unsafe impl ExtensionLibrary for MyExtension {}
 
 
-#[derive(PartialEq, Eq, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
 enum CellRules{
     Empty,
     ForceEmpty,
@@ -50,7 +61,6 @@ impl CellRules{
         let id:u16 = tile.unwrap().get_custom_data(layer_name.clone()).to();
         CellRules::from_id(id)
     }
-    #[allow(unused)]
     fn to_id(&self)->u16{
         match self{
             Self::ForceEmpty=>0,
@@ -63,10 +73,18 @@ impl CellRules{
         match self{
             Self::Empty => 0,
             Self::BasicFilled => 6,
-            Self::PermaCell => panic!("user probably shouldn't be able to place these, too op"),
+            //they never die, so they're priced well above a basic cell
+            Self::PermaCell => 20,
             Self::ForceEmpty => 0
         }
     }
+    //seeds needed on top of the energy cost
+    fn to_seed_cost(&self) -> i32{
+        match self{
+            Self::PermaCell => 1,
+            _default => 0
+        }
+    }
     fn can_set(&self)-> bool{
         match self {
             Self::ForceEmpty=> false,
@@ -109,6 +127,13 @@ impl CellRules{
             Self::PermaCell=>Self::PermaCell
         }
     }
+    fn is_alive(&self)-> bool{
+        match self {
+            Self::BasicFilled=>true,
+            Self::PermaCell=>true,
+            _default=>false
+        }
+    }
     #[allow(unused)]
     fn user_replaceable(&self)-> bool{
         match self {
@@ -150,6 +175,7 @@ impl CellRules{
     }
 }
 
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 enum CellEvents{
     OverpopulateDeath,
     ExtraOverpopulateDeath,
@@ -157,6 +183,9 @@ enum CellEvents{
 }
 
 impl CellEvents{
+    //ordered by event index
+    const ALL: [CellEvents; 3] = [Self::OverpopulateDeath, Self::ExtraOverpopulateDeath, Self::CellCreate];
+
     fn get_event_name(&self) -> &str{
         match self{
             Self::OverpopulateDeath=>"overpopulate_death",
@@ -211,6 +240,9 @@ struct SmallProjectile{
     direction: f32,
     #[export]
     power: i32,
+    //put on enemies hit that have a StatusEffects node
+    #[export]
+    status_effect: Option<Gd<StatusEffect>>,
     #[export]
     hitbox: Option<Gd<Area2D>>,
     disabled: bool
@@ -220,7 +252,10 @@ struct SmallProjectile{
 impl ISprite2D for SmallProjectile {
     fn ready(&mut self){
         self.disabled = false;
-        self.hitbox.clone().expect("missing hitbox!").connect("area_entered".into(), Callable::from_object_method(&self.base_mut(), "on_area_entered"));
+        let mut hitbox = self.hitbox.clone().expect("missing hitbox!");
+        hitbox.set_collision_layer(collision_layers::PROJECTILE);
+        hitbox.set_collision_mask(collision_layers::ENEMY_HURTBOX);
+        hitbox.connect("area_entered".into(), Callable::from_object_method(&self.base_mut(), "on_area_entered"));
     }
     fn physics_process(&mut self, delta: f64){
         let direction = self.direction * std::f32::consts::PI * 2.;
@@ -237,14 +272,25 @@ impl SmallProjectile{
         if self.disabled{
             return;
         }
-        let parent = area.get_parent().unwrap();
-        let mut damageable = Damageable::find(parent);
-        damageable.bind_mut().take_damage(self.power);
+        let Some(mut damageable) = Damageable::find(area.upcast()) else{
+            return;
+        };
+        let enemy = damageable.get_parent().unwrap();
+        if let Some(effect) = self.get_status_effect(){
+            if let Some(mut status_effects) = find_component::<StatusEffects>(&enemy){
+                status_effects.bind_mut().apply(effect);
+            }
+        }
+        damageable.bind_mut().take_damage(self.power, Some(self.to_gd().upcast()));
         self.disabled = true;
         self.base_mut().queue_free();
     }
 }
 
+const DAMAGED_SIGNAL: &str = "damaged";
+const HEALED_SIGNAL: &str = "healed";
+const DIED_SIGNAL: &str = "died";
+
 #[derive(GodotClass)]
 #[class(base = Node,init)]
 struct Damageable{
@@ -252,19 +298,164 @@ struct Damageable{
     #[export]
     max_health: i32,
     #[export]
-    current_health: i32
+    current_health: i32,
+    //energy given to the player when this dies
+    #[export]
+    bounty: i32,
+    //seconds after taking damage during which further hits are ignored
+    #[export]
+    invulnerability_time: f64,
+    invulnerable_for: f64,
+    dead: bool
+}
+
+#[godot_api]
+impl INode for Damageable{
+    fn ready(&mut self){
+        let pay_bounty = Callable::from_object_method(&self.base_mut(), "pay_bounty");
+        self.base_mut().connect(DIED_SIGNAL.into(), pay_bounty);
+    }
+    fn physics_process(&mut self, delta: f64){
+        self.invulnerable_for = invulnerability_left(self.invulnerable_for, delta);
+    }
+}
+
+#[godot_api]
+impl Damageable{
+    //source is whatever dealt the damage, null if unknown
+    #[signal]
+    fn damaged(amount: i32, source: Gd<Node>);
+    #[signal]
+    fn healed(amount: i32);
+    #[signal]
+    fn died(source: Gd<Node>);
+
+    #[func]
+    pub fn is_dead(&self) -> bool{
+        self.dead
+    }
+    #[func]
+    pub fn is_invulnerable(&self) -> bool{
+        self.invulnerable_for > 0.
+    }
+    //ignore damage for the given seconds, on top of any invulnerability already running
+    #[func]
+    pub fn make_invulnerable(&mut self, seconds: f64){
+        self.invulnerable_for = self.invulnerable_for.max(seconds);
+    }
+    //clamped to max_health, does nothing once dead
+    #[func]
+    pub fn heal(&mut self, amount: i32){
+        let healed = heal_amount(self.current_health, self.max_health, amount, self.dead);
+        if healed <= 0{
+            return;
+        }
+        self.current_health += healed;
+        self.base_mut().emit_signal(HEALED_SIGNAL.into(), &[Variant::from(healed)]);
+    }
+    //connected to died in ready
+    #[func]
+    fn pay_bounty(&self, _source: Variant){
+        if self.bounty <= 0{
+            return;
+        }
+        if let Some(mut energy) = PlayerEnergy::find(self.base().get_tree().unwrap()){
+            energy.bind_mut().gain_energy(self.bounty, EnergyReason::Bounty);
+        }
+    }
 }
 
 impl Damageable {
-    pub fn take_damage(&mut self, amount: i32){
-        if amount >= self.current_health{
-            self.base_mut().get_parent().unwrap().queue_free();
+    //a hit, reduced by Armor and Shield and ignored while invulnerable
+    pub fn take_damage(&mut self, amount: i32, source: Option<Gd<Node>>){
+        self.apply_damage(amount, source, DamageKind::Hit);
+    }
+    //damage over time, like burning, goes straight to health and leaves invulnerability alone
+    pub fn take_tick_damage(&mut self, amount: i32, source: Option<Gd<Node>>){
+        self.apply_damage(amount, source, DamageKind::Tick);
+    }
+    fn apply_damage(&mut self, amount: i32, source: Option<Gd<Node>>, kind: DamageKind){
+        if self.dead{
+            return;
+        }
+        let Some(invulnerable_for) = invulnerability_after(kind, self.invulnerable_for, self.invulnerability_time) else{
+            return;
+        };
+        let mut enemy = self.base().get_parent().unwrap();
+        let mut amount = amount;
+        if kind == DamageKind::Hit{
+            if let Some(armor) = find_component::<Armor>(&enemy){
+                amount = armor.bind().reduce(amount);
+            }
+            if let Some(mut shield) = find_component::<Shield>(&enemy){
+                amount = shield.bind_mut().absorb(amount);
+            }
+        }
+        let (amount, killed) = damage_taken(self.current_health, amount, self.dead);
+        if amount <= 0 && !killed{
+            return;
         }
         self.current_health -= amount;
+        self.invulnerable_for = invulnerable_for;
+        let source = source.map_or(Variant::nil(), Variant::from);
+        self.base_mut().emit_signal(DAMAGED_SIGNAL.into(), &[Variant::from(amount), source.clone()]);
+        if killed{
+            self.dead = true;
+            self.on_death(&enemy);
+            self.base_mut().emit_signal(DIED_SIGNAL.into(), &[source]);
+            enemy.queue_free();
+        }
+    }
+    fn on_death(&self, enemy: &Gd<Node>){
+        if let Some(splitter) = find_component::<Splitter>(enemy){
+            splitter.bind().split();
+        }
     }
-    pub fn find(parent: Gd<Node>)->Gd<Damageable>{
-        parent.find_child("Damageable".into()).unwrap().cast()
+    //the Damageable of the enemy node belongs to, looking at node and then its parent since hurtboxes
+    //sit directly under the enemy, None for anything that can't be damaged
+    pub fn find(node: Gd<Node>) -> Option<Gd<Damageable>>{
+        find_component::<Damageable>(&node)
+            .or_else(|| node.get_parent().and_then(|enemy| find_component::<Damageable>(&enemy)))
+    }
+}
+
+//how much of a hit comes off of current_health and whether it's the killing blow,
+//nothing lands on something already dead so it can only die once
+fn damage_taken(current_health: i32, amount: i32, dead: bool) -> (i32, bool){
+    if dead || amount <= 0{
+        return (0, false);
     }
+    let taken = amount.min(current_health.max(0));
+    (taken, current_health - taken <= 0)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum DamageKind{
+    Hit,
+    Tick
+}
+
+//invulnerability left once damage of kind lands, None if it's blocked,
+//only hits are blocked and only hits start a new invulnerability window
+fn invulnerability_after(kind: DamageKind, invulnerable_for: f64, invulnerability_time: f64) -> Option<f64>{
+    match kind{
+        DamageKind::Hit if invulnerable_for > 0. => None,
+        DamageKind::Hit => Some(invulnerability_time),
+        DamageKind::Tick => Some(invulnerable_for)
+    }
+}
+
+//invulnerability still running after delta seconds
+fn invulnerability_left(invulnerable_for: f64, delta: f64) -> f64{
+    (invulnerable_for - delta).max(0.)
+}
+
+//how much of a heal actually lands, never past max_health
+fn heal_amount(current_health: i32, max_health: i32, amount: i32, dead: bool) -> i32{
+    if dead || amount <= 0{
+        return 0;
+    }
+    (current_health + amount).min(max_health) - current_health
 }
 
 #[derive(GodotClass)]
@@ -277,15 +468,34 @@ struct BasicEnemy{
 
 #[godot_api]
 impl IPathFollow2D for BasicEnemy{
+    fn ready(&mut self){
+        //the end of the path is checked against its length, so progress shouldn't wrap around
+        self.base_mut().set_loop(false);
+        self.base_mut().add_to_group(ENEMY_GROUP.into());
+    }
     fn physics_process(&mut self, delta: f64){
         let mut p = self.base().get_progress();
-        let last_progress = self.base().get_progress_ratio();
-        p += self.speed * delta as f32;
+        let speed_multiplier = find_component::<StatusEffects>(&self.to_gd().upcast())
+            .map_or(1., |status_effects| status_effects.bind().speed_multiplier())
+            * find_component::<Boss>(&self.to_gd().upcast()).map_or(1., |boss| boss.bind().speed_multiplier());
+        p += self.speed * speed_multiplier * delta as f32;
         self.base_mut().set_progress(p);
-        if self.base().get_progress_ratio() < last_progress{
-            let spawner:Gd<EnemyPath> = self.base().get_parent().unwrap().cast();
-            spawner.bind().hit_player();
-            self.base_mut().queue_free();
+        let path: Gd<EnemyPath> = self.base().get_parent().unwrap().cast();
+        let length = path.bind().get_length();
+        if p < length{
+            return;
+        }
+        let branch = path.bind().choose_branch();
+        match branch{
+            Some(branch) => {
+                //the path's children can't change while physics is running, both calls run in order once it's done
+                self.base_mut().call_deferred("reparent".into(), &[Variant::from(branch)]);
+                self.base_mut().call_deferred("set_progress".into(), &[Variant::from(p - length)]);
+            }
+            None => {
+                path.bind().hit_player();
+                self.base_mut().queue_free();
+            }
         }
     }
 }
@@ -333,4 +543,89 @@ pub mod player_health;
 pub mod ingame_state_tracker;
 pub mod defense_layer;
 pub mod selected_hotbar;
-pub mod pause_state_manager;
\ No newline at end of file
+pub mod pause_state_manager;
+pub mod cost_table;
+pub mod edit_history;
+pub mod draw_tools;
+pub mod cell_grid;
+pub mod blueprint_sandbox;
+pub mod pattern_analysis;
+pub mod demolish_tool;
+pub mod capture_tool;
+pub mod blueprint_code;
+pub mod player_progress;
+pub mod resource_wallet;
+pub mod wave_spawner;
+pub mod enemy_components;
+pub mod status_effects;
+pub mod flow_field;
+pub mod boss;
+pub mod collision_layers;
+#[cfg(test)]
+mod test_helpers;
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    #[test]
+    fn killing_blow_lands_once(){
+        let mut health = 3;
+        let mut dead = false;
+        let mut deaths = 0;
+        for _ in 0..4{
+            let (taken, killed) = damage_taken(health, 2, dead);
+            health -= taken;
+            if killed{
+                dead = true;
+                deaths += 1;
+            }
+        }
+        assert_eq!(deaths, 1);
+        assert_eq!(health, 0);
+    }
+
+    #[test]
+    fn overkill_is_clamped(){
+        assert_eq!(damage_taken(3, 10, false), (3, true));
+        assert_eq!(damage_taken(3, 2, false), (2, false));
+        assert_eq!(damage_taken(3, 0, false), (0, false));
+        assert_eq!(damage_taken(3, 10, true), (0, false));
+    }
+
+    #[test]
+    fn every_hit_restarts_invulnerability(){
+        let mut invulnerable_for = invulnerability_after(DamageKind::Hit, 0., 0.5).unwrap();
+        assert_eq!(invulnerable_for, 0.5);
+        invulnerable_for = invulnerability_left(invulnerable_for, 0.2);
+        assert_eq!(invulnerability_after(DamageKind::Hit, invulnerable_for, 0.5), None);
+        invulnerable_for = invulnerability_left(invulnerable_for, 0.4);
+        assert_eq!(invulnerable_for, 0.);
+        assert_eq!(invulnerability_after(DamageKind::Hit, invulnerable_for, 0.5), Some(0.5));
+    }
+
+    #[test]
+    fn no_invulnerability_time_never_blocks(){
+        assert_eq!(invulnerability_after(DamageKind::Hit, 0., 0.), Some(0.));
+        assert_eq!(invulnerability_after(DamageKind::Hit, invulnerability_left(0., 0.016), 0.), Some(0.));
+    }
+
+    #[test]
+    fn ticks_ignore_invulnerability(){
+        //a burn tick lands during a hit's window and doesn't restart it
+        assert_eq!(invulnerability_after(DamageKind::Tick, 0.3, 0.5), Some(0.3));
+        assert_eq!(invulnerability_after(DamageKind::Hit, 0.3, 0.5), None);
+        //and after a tick with no window running, the next hit still lands
+        assert_eq!(invulnerability_after(DamageKind::Tick, 0., 0.5), Some(0.));
+        assert_eq!(invulnerability_after(DamageKind::Hit, 0., 0.5), Some(0.5));
+    }
+
+    #[test]
+    fn heal_is_clamped(){
+        assert_eq!(heal_amount(3, 5, 10, false), 2);
+        assert_eq!(heal_amount(3, 5, 1, false), 1);
+        assert_eq!(heal_amount(5, 5, 1, false), 0);
+        assert_eq!(heal_amount(3, 5, -1, false), 0);
+        assert_eq!(heal_amount(0, 5, 3, true), 0);
+    }
+}
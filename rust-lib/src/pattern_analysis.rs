@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use godot::builtin::Dictionary;
+use godot::builtin::Vector2;
+use godot::builtin::Vector2i;
+
+use crate::CellRules;
+
+type SparseCells = HashMap<(i32, i32), CellRules>;
+
+//past this many live cells the run stops and the pattern counts as growing, keeps breeders from stalling the editor
+const MAX_POPULATION: usize = 2000;
+//live cells at most this many tiles apart belong to the same component
+const COMPONENT_GAP: i32 = 2;
+//generations a component that split off gets to show it's a spaceship
+const EMITTED_GENERATIONS: i32 = 16;
+
+pub enum PatternKind{
+    DiesOut,
+    StillLife,
+    Oscillator{ period: i32 },
+    Spaceship{ period: i32, displacement: Vector2i },
+    //keeps sending spaceships away from where it started, like a gun
+    Emitter{ emitted: i32 },
+    UnboundedGrowth,
+    Undetermined
+}
+
+impl PatternKind{
+    fn get_name(&self) -> &str{
+        match self{
+            Self::DiesOut => "dies_out",
+            Self::StillLife => "still_life",
+            Self::Oscillator { .. } => "oscillator",
+            Self::Spaceship { .. } => "spaceship",
+            Self::Emitter { .. } => "emitter",
+            Self::UnboundedGrowth => "unbounded_growth",
+            Self::Undetermined => "undetermined"
+        }
+    }
+}
+
+pub struct PatternAnalysis{
+    pub kind: PatternKind,
+    //generation the result was determined at, or the generation limit
+    pub generations: i32,
+    pub initial_size: Vector2i,
+    pub final_size: Vector2i
+}
+
+impl PatternAnalysis{
+    pub fn to_dictionary(&self) -> Dictionary{
+        let (period, displacement) = match self.kind{
+            PatternKind::StillLife => (1, Vector2i::ZERO),
+            PatternKind::Oscillator { period } => (period, Vector2i::ZERO),
+            PatternKind::Spaceship { period, displacement } => (period, displacement),
+            _default => (0, Vector2i::ZERO)
+        };
+        let velocity = if period > 0 { displacement.cast_float() / period as f32 } else { Vector2::ZERO };
+        let emitted = match self.kind{
+            PatternKind::Emitter { emitted } => emitted,
+            _default => 0
+        };
+        let mut result = Dictionary::new();
+        result.set("kind", self.kind.get_name());
+        result.set("period", period);
+        result.set("displacement", displacement);
+        result.set("emitted", emitted);
+        result.set("velocity", velocity);
+        result.set("generations", self.generations);
+        result.set("initial_size", self.initial_size);
+        result.set("final_size", self.final_size);
+        result
+    }
+}
+
+//runs the cells on an unbounded empty plane for up to max_generations and classifies what happens
+pub fn analyze(cells: &Vec<(Vector2i, CellRules)>, max_generations: i32) -> PatternAnalysis{
+    let current: SparseCells = cells.iter()
+        .filter(|(_, c)| c.is_alive())
+        .map(|(p, c)| ((p.x, p.y), c.clone()))
+        .collect();
+    simulate(current, max_generations, true)
+}
+
+fn simulate(mut current: SparseCells, max_generations: i32, detect_emitters: bool) -> PatternAnalysis{
+    let (initial_key, initial_offset, initial_size) = normalize(&current);
+    let mut seen = HashMap::from([(initial_key, (0, initial_offset))]);
+    let mut sizes = vec![initial_size];
+
+    for generation in 1..=max_generations{
+        current = step(&current);
+        if current.is_empty(){
+            return PatternAnalysis { kind: PatternKind::DiesOut, generations: generation, initial_size, final_size: Vector2i::ZERO };
+        }
+        let (key, offset, size) = normalize(&current);
+        if current.len() > MAX_POPULATION{
+            return PatternAnalysis { kind: PatternKind::UnboundedGrowth, generations: generation, initial_size, final_size: size };
+        }
+        sizes.push(size);
+        if let Some((earlier, earlier_offset)) = seen.get(&key){
+            let period = generation - earlier;
+            let displacement = offset - *earlier_offset;
+            let kind = if displacement != Vector2i::ZERO{
+                PatternKind::Spaceship { period, displacement }
+            }else if period == 1{
+                PatternKind::StillLife
+            }else{
+                PatternKind::Oscillator { period }
+            };
+            return PatternAnalysis { kind, generations: generation, initial_size, final_size: size };
+        }
+        seen.insert(key, (generation, offset));
+    }
+
+    //no repeat found, spaceships flying off make the bounding box grow too so check for those before calling it growth,
+    //otherwise it's growth if the bounding box ends up bigger than anything in the first half of the run
+    let final_size = *sizes.last().unwrap();
+    let first_half_max = sizes[..sizes.len() / 2 + 1].iter().map(|s| s.x * s.y).max().unwrap_or(0);
+    let emitted = if detect_emitters { count_emitted(&current, initial_offset, initial_size) } else { 0 };
+    let kind = if emitted > 0{
+        PatternKind::Emitter { emitted }
+    }else if final_size.x * final_size.y > first_half_max{
+        PatternKind::UnboundedGrowth
+    }else{
+        PatternKind::Undetermined
+    };
+    PatternAnalysis { kind, generations: max_generations, initial_size, final_size }
+}
+
+//spaceships that split off and are flying away from the area the pattern started in,
+//0 if anything else split off since then it could be growing on its own
+fn count_emitted(cells: &SparseCells, initial_offset: Vector2i, initial_size: Vector2i) -> i32{
+    let initial_center = initial_offset.cast_float() + initial_size.cast_float() / 2.;
+    let mut emitted = 0;
+    for component in components(cells){
+        let (_, offset, size) = normalize(&component);
+        if near(offset, size, initial_offset, initial_size){
+            continue;
+        }
+        let center = offset.cast_float() + size.cast_float() / 2.;
+        match simulate(component, EMITTED_GENERATIONS, false).kind{
+            PatternKind::Spaceship { displacement, .. } if (center - initial_center).dot(displacement.cast_float()) > 0. => emitted += 1,
+            _default => return 0
+        }
+    }
+    emitted
+}
+
+//groups of live cells at most COMPONENT_GAP tiles apart
+fn components(cells: &SparseCells) -> Vec<SparseCells>{
+    let mut remaining: HashSet<(i32, i32)> = cells.keys().cloned().collect();
+    let mut result = vec![];
+    loop{
+        let Some(start) = remaining.iter().next().cloned() else{
+            break;
+        };
+        remaining.remove(&start);
+        let mut component = SparseCells::new();
+        let mut stack = vec![start];
+        while let Some((x, y)) = stack.pop(){
+            component.insert((x, y), cells[&(x, y)].clone());
+            for dy in -COMPONENT_GAP..=COMPONENT_GAP{
+                for dx in -COMPONENT_GAP..=COMPONENT_GAP{
+                    if remaining.remove(&(x + dx, y + dy)){
+                        stack.push((x + dx, y + dy));
+                    }
+                }
+            }
+        }
+        result.push(component);
+    }
+    result
+}
+
+//whether two bounding boxes are at most COMPONENT_GAP tiles apart
+fn near(offset: Vector2i, size: Vector2i, other_offset: Vector2i, other_size: Vector2i) -> bool{
+    offset.x <= other_offset.x + other_size.x - 1 + COMPONENT_GAP
+        && other_offset.x <= offset.x + size.x - 1 + COMPONENT_GAP
+        && offset.y <= other_offset.y + other_size.y - 1 + COMPONENT_GAP
+        && other_offset.y <= offset.y + size.y - 1 + COMPONENT_GAP
+}
+
+fn step(cells: &SparseCells) -> SparseCells{
+    let mut candidates = HashSet::new();
+    for (x, y) in cells.keys(){
+        for dy in -1..=1{
+            for dx in -1..=1{
+                candidates.insert((x + dx, y + dy));
+            }
+        }
+    }
+    let mut next = SparseCells::new();
+    for (x, y) in candidates{
+        let mut neighbors = vec![];
+        for dy in -1..=1{
+            for dx in -1..=1{
+                if dx != 0 || dy != 0{
+                    neighbors.push(cells.get(&(x + dx, y + dy)).cloned().unwrap_or(CellRules::Empty));
+                }
+            }
+        }
+        let cell = cells.get(&(x, y)).cloned().unwrap_or(CellRules::Empty);
+        let t = cell.next_cell(&neighbors);
+        if t.is_alive(){
+            next.insert((x, y), t);
+        }
+    }
+    next
+}
+
+//shape of the cells moved to the origin, along with where it was moved from and its bounding box size
+fn normalize(cells: &SparseCells) -> (Vec<(i32, i32, CellRules)>, Vector2i, Vector2i){
+    let min_x = cells.keys().map(|(x, _)| *x).min().unwrap_or(0);
+    let min_y = cells.keys().map(|(_, y)| *y).min().unwrap_or(0);
+    let max_x = cells.keys().map(|(x, _)| *x).max().unwrap_or(-1);
+    let max_y = cells.keys().map(|(_, y)| *y).max().unwrap_or(-1);
+    let mut key: Vec<(i32, i32, CellRules)> = cells.iter()
+        .map(|((x, y), c)| (x - min_x, y - min_y, c.clone()))
+        .collect();
+    key.sort_by_key(|(x, y, _)| (*x, *y));
+    (key, Vector2i::new(min_x, min_y), Vector2i::new(max_x - min_x + 1, max_y - min_y + 1))
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+
+    //'#' is a live cell, anything else is empty
+    fn cells_from(rows: &[&str]) -> Vec<(Vector2i, CellRules)>{
+        let mut cells = vec![];
+        for (y, row) in rows.iter().enumerate(){
+            for (x, c) in row.chars().enumerate(){
+                if c == '#'{
+                    cells.push((Vector2i::new(x as i32, y as i32), CellRules::BasicFilled));
+                }
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn block_is_still_life(){
+        let analysis = analyze(&cells_from(&["##", "##"]), 60);
+        assert!(matches!(analysis.kind, PatternKind::StillLife));
+        assert_eq!(analysis.generations, 1);
+    }
+
+    #[test]
+    fn blinker_is_oscillator(){
+        let analysis = analyze(&cells_from(&["###"]), 60);
+        assert!(matches!(analysis.kind, PatternKind::Oscillator { period: 2 }));
+    }
+
+    #[test]
+    fn glider_is_spaceship(){
+        let analysis = analyze(&cells_from(&[".#", "..#", "###"]), 60);
+        match analysis.kind{
+            PatternKind::Spaceship { period, displacement } => {
+                assert_eq!(period, 4);
+                assert_eq!(displacement, Vector2i::new(1, 1));
+            }
+            _default => panic!("glider is not a spaceship")
+        }
+    }
+
+    #[test]
+    fn pair_dies_out(){
+        let analysis = analyze(&cells_from(&["##"]), 60);
+        assert!(matches!(analysis.kind, PatternKind::DiesOut));
+        assert_eq!(analysis.generations, 1);
+    }
+
+    #[test]
+    fn glider_gun_is_emitter(){
+        let gun = cells_from(&[
+            "........................#...........",
+            "......................#.#...........",
+            "............##......##............##",
+            "...........#...#....##............##",
+            "##........#.....#...##..............",
+            "##........#...#.##....#.#...........",
+            "..........#.....#.......#...........",
+            "...........#...#....................",
+            "............##......................"
+        ]);
+        let analysis = analyze(&gun, 100);
+        assert!(matches!(analysis.kind, PatternKind::Emitter { emitted: 3 }));
+    }
+
+    #[test]
+    fn population_is_capped(){
+        //a long line turns into a thick bar on the first step
+        let line: Vec<(Vector2i, CellRules)> = (0..1000).map(|x| (Vector2i::new(x, 0), CellRules::BasicFilled)).collect();
+        let analysis = analyze(&line, 60);
+        assert!(matches!(analysis.kind, PatternKind::UnboundedGrowth));
+        assert_eq!(analysis.generations, 1);
+    }
+}
@@ -0,0 +1,84 @@
+use godot::builtin::GString;
+use godot::builtin::PackedInt32Array;
+use godot::builtin::PackedStringArray;
+use godot::builtin::Variant;
+use godot::classes::ConfigFile;
+use godot::global::godot_warn;
+use godot::global::Error;
+use godot::obj::Gd;
+use godot::obj::NewGd;
+
+const PROGRESS_PATH: &str = "user://progress.cfg";
+const UNLOCKS_SECTION: &str = "unlocks";
+const COMPLETED_LEVELS_KEY: &str = "completed_levels";
+const BLUEPRINTS_KEY: &str = "blueprints";
+const BRUSH_TILES_KEY: &str = "brush_tiles";
+
+//what the player has earned across levels, kept in a config file in user://
+pub struct PlayerProgress{
+    config: Gd<ConfigFile>
+}
+
+impl PlayerProgress{
+    //starts with nothing unlocked if there's no saved progress yet
+    pub fn load() -> Self{
+        let mut config = ConfigFile::new_gd();
+        let result = config.load(PROGRESS_PATH.into());
+        if result != Error::OK && result != Error::ERR_FILE_NOT_FOUND{
+            godot_warn!("failed to load progress from {}: {:?}", PROGRESS_PATH, result);
+        }
+        Self { config }
+    }
+    pub fn save(&mut self){
+        let result = self.config.save(PROGRESS_PATH.into());
+        if result != Error::OK{
+            godot_warn!("failed to save progress to {}: {:?}", PROGRESS_PATH, result);
+        }
+    }
+
+    pub fn is_level_completed(&self, level: &GString) -> bool{
+        self.get_strings(COMPLETED_LEVELS_KEY).contains(level)
+    }
+    pub fn is_blueprint_unlocked(&self, unlock_id: &GString) -> bool{
+        self.get_strings(BLUEPRINTS_KEY).contains(unlock_id)
+    }
+    pub fn is_brush_tile_unlocked(&self, tile: u16) -> bool{
+        self.get_brush_tiles().contains(tile as i32)
+    }
+
+    pub fn complete_level(&mut self, level: GString){
+        self.add_string(COMPLETED_LEVELS_KEY, level);
+    }
+    pub fn unlock_blueprint(&mut self, unlock_id: GString){
+        self.add_string(BLUEPRINTS_KEY, unlock_id);
+    }
+    pub fn unlock_brush_tile(&mut self, tile: u16){
+        let mut tiles = self.get_brush_tiles();
+        if !tiles.contains(tile as i32){
+            tiles.push(tile as i32);
+            self.config.set_value(UNLOCKS_SECTION.into(), BRUSH_TILES_KEY.into(), Variant::from(tiles));
+        }
+    }
+
+    fn get_strings(&self, key: &str) -> PackedStringArray{
+        self.config.get_value_ex(UNLOCKS_SECTION.into(), key.into())
+            .default(Variant::from(PackedStringArray::new()))
+            .done()
+            .try_to()
+            .unwrap_or_default()
+    }
+    fn add_string(&mut self, key: &str, value: GString){
+        let mut values = self.get_strings(key);
+        if !values.contains(&value){
+            values.push(value);
+            self.config.set_value(UNLOCKS_SECTION.into(), key.into(), Variant::from(values));
+        }
+    }
+    fn get_brush_tiles(&self) -> PackedInt32Array{
+        self.config.get_value_ex(UNLOCKS_SECTION.into(), BRUSH_TILES_KEY.into())
+            .default(Variant::from(PackedInt32Array::new()))
+            .done()
+            .try_to()
+            .unwrap_or_default()
+    }
+}
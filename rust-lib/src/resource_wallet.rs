@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+
+use godot::builtin::Dictionary;
+use godot::builtin::GString;
+use godot::builtin::StringName;
+use godot::classes::INode;
+use godot::classes::Node;
+use godot::classes::Range;
+use godot::obj::Base;
+use godot::obj::Gd;
+use godot::prelude::godot_api;
+use godot::prelude::GodotClass;
+
+//name of the resource PlayerEnergy keeps itself, every other resource lives in a ResourcePool
+pub const ENERGY: &str = "energy";
+pub const SEEDS: &str = "seeds";
+
+//amounts of named resources, zero amounts are left out
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct ResourceCost{
+    amounts: BTreeMap<String, i32>
+}
+
+impl ResourceCost{
+    pub fn energy(amount: i32) -> Self{
+        let mut cost = Self::default();
+        cost.add(ENERGY, amount);
+        cost
+    }
+    pub fn add(&mut self, resource: &str, amount: i32){
+        let total = self.get(resource) + amount;
+        if total == 0{
+            self.amounts.remove(resource);
+        }else{
+            self.amounts.insert(resource.to_string(), total);
+        }
+    }
+    pub fn merge(&mut self, other: &ResourceCost){
+        for (resource, amount) in other.iter(){
+            self.add(resource, *amount);
+        }
+    }
+    pub fn get(&self, resource: &str) -> i32{
+        self.amounts.get(resource).copied().unwrap_or(0)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &i32)>{
+        self.amounts.iter()
+    }
+    //{"resource name": amount}
+    pub fn to_dictionary(&self) -> Dictionary{
+        let mut result = Dictionary::new();
+        for (resource, amount) in self.iter(){
+            result.set(GString::from(resource.as_str()), *amount);
+        }
+        result
+    }
+    pub fn from_dictionary(dictionary: &Dictionary) -> Self{
+        let mut cost = Self::default();
+        for (resource, amount) in dictionary.iter_shared(){
+            //keys written as &"name" in gdscript are StringNames
+            let resource = resource.try_to::<GString>().map(|name| name.to_string())
+                .or_else(|_| resource.try_to::<StringName>().map(|name| name.to_string()));
+            if let (Ok(resource), Ok(amount)) = (resource, amount.try_to::<i32>()){
+                cost.add(&resource, amount);
+            }
+        }
+        cost
+    }
+}
+
+//a resource the player collects besides energy, listed in PlayerEnergy's pools
+#[derive(GodotClass)]
+#[class(base = Node, init)]
+pub struct ResourcePool{
+    base: Base<Node>,
+    #[export]
+    resource_name: GString,
+    #[export]
+    amount: i32,
+    #[export]
+    max_amount: i32,
+    #[export]
+    display: Option<Gd<Range>>
+}
+
+#[godot_api]
+impl INode for ResourcePool {
+    fn ready(&mut self){
+        let max_amount = self.max_amount;
+        if let Some(mut display) = self.get_display(){
+            display.set_max(max_amount as f64);
+        }
+        self.update_display();
+    }
+}
+
+impl ResourcePool{
+    //sets the amount kept within 0 and max_amount and returns it
+    pub fn set_amount_clamped(&mut self, amount: i32) -> i32{
+        self.amount = amount.clamp(0, self.max_amount.max(0));
+        self.update_display();
+        self.amount
+    }
+    fn update_display(&self){
+        if let Some(mut display) = self.get_display(){
+            display.set_value(self.amount as f64);
+        }
+    }
+}
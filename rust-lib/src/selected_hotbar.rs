@@ -1,4 +1,4 @@
-use godot::{builtin::Vector2, classes::TextureRect, obj::{Base, WithBaseField}, prelude::{godot_api, GodotClass}};
+use godot::{builtin::{Array, Vector2}, classes::{Texture2D, TextureRect}, obj::{Base, Gd, NewAlloc, WithBaseField}, prelude::{godot_api, GodotClass}};
 
 #[derive(GodotClass)]
 #[class(base = TextureRect, init)]
@@ -7,7 +7,12 @@ pub struct SelectedHotbar{
     #[export]
     option_offset: Vector2,
     #[export]
-    min_pos: Vector2
+    min_pos: Vector2,
+    //when set one of these is laid out behind every option, cycling through them if there are more options than icons,
+    //so the bar can grow past what its parent's texture shows
+    #[export]
+    option_icons: Array<Gd<Texture2D>>,
+    icons: Vec<Gd<TextureRect>>
 }
 
 #[godot_api]
@@ -18,4 +23,26 @@ impl SelectedHotbar{
         new_transform += index as f32 * self.option_offset;
         self.base_mut().set_position(new_transform);
     }
-}
\ No newline at end of file
+    //lays out an icon for each of count options in the parent, does nothing without option_icons
+    #[func]
+    pub fn set_option_count(&mut self, count: u32){
+        if self.option_icons.is_empty() || self.icons.len() == count as usize{
+            return;
+        }
+        for mut icon in self.icons.drain(..){
+            icon.queue_free();
+        }
+        let mut parent = self.base().get_parent().expect("hotbar has no parent");
+        let size = self.base().get_size();
+        for index in 0..count{
+            let texture = self.option_icons.get(index as usize % self.option_icons.len()).unwrap();
+            let mut icon = TextureRect::new_alloc();
+            icon.set_position(self.min_pos + index as f32 * self.option_offset + (size - texture.get_size()) / 2.);
+            icon.set_texture(texture);
+            parent.add_child(icon.clone().upcast());
+            //behind the selection highlight
+            parent.move_child(icon.clone().upcast(), 0);
+            self.icons.push(icon);
+        }
+    }
+}
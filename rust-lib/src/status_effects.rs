@@ -0,0 +1,149 @@
+use godot::classes::INode;
+use godot::classes::Node;
+use godot::classes::Resource;
+use godot::obj::Base;
+use godot::obj::Gd;
+use godot::obj::WithBaseField;
+use godot::prelude::godot_api;
+use godot::prelude::Export;
+use godot::prelude::GodotClass;
+use godot::prelude::GodotConvert;
+use godot::prelude::Var;
+
+use crate::enemy_components::find_component;
+use crate::Damageable;
+
+#[derive(GodotConvert, Var, Export, Default, PartialEq, Eq, Clone, Copy, Debug)]
+#[godot(via = i64)]
+pub enum StatusKind{
+    //multiplies speed by 1 - strength
+    #[default]
+    Slow = 0,
+    //deals strength damage every tick_interval seconds
+    Burn = 1,
+    //stops the enemy
+    Stun = 2,
+}
+
+#[derive(GodotConvert, Var, Export, Default, PartialEq, Eq, Clone, Copy, Debug)]
+#[godot(via = i64)]
+pub enum StackRule{
+    //reapplying restarts the duration and keeps the stronger strength
+    #[default]
+    Refresh = 0,
+    //every application runs on its own, up to max_stacks at once
+    Stack = 1,
+    //reapplying does nothing while the effect is active
+    Ignore = 2,
+}
+
+//an effect a projectile or cell event puts on the enemies it hits
+#[derive(GodotClass)]
+#[class(base = Resource, init)]
+pub struct StatusEffect{
+    base: Base<Resource>,
+    #[export]
+    kind: StatusKind,
+    #[export]
+    duration: f64,
+    #[export]
+    strength: f32,
+    #[export]
+    tick_interval: f64,
+    #[export]
+    stacking: StackRule,
+    #[export]
+    max_stacks: i32
+}
+
+struct ActiveEffect{
+    effect: Gd<StatusEffect>,
+    kind: StatusKind,
+    strength: f32,
+    remaining: f64,
+    tick_timer: f64
+}
+
+//effects currently on an enemy, added as a child of the enemy like the other enemy components
+#[derive(GodotClass)]
+#[class(base = Node, init)]
+pub struct StatusEffects{
+    base: Base<Node>,
+    active: Vec<ActiveEffect>
+}
+
+#[godot_api]
+impl INode for StatusEffects{
+    fn physics_process(&mut self, delta: f64){
+        let mut burn_damage = 0;
+        for active in self.active.iter_mut(){
+            active.remaining -= delta;
+            if active.kind != StatusKind::Burn{
+                continue;
+            }
+            let tick_interval = active.effect.bind().get_tick_interval();
+            active.tick_timer += delta;
+            while tick_interval > 0. && active.tick_timer >= tick_interval{
+                active.tick_timer -= tick_interval;
+                burn_damage += active.strength.round() as i32;
+            }
+        }
+        self.active.retain(|active| active.remaining > 0.);
+        if burn_damage > 0{
+            let enemy = self.base().get_parent().unwrap();
+            if let Some(mut damageable) = find_component::<Damageable>(&enemy){
+                damageable.bind_mut().take_tick_damage(burn_damage, Some(self.to_gd().upcast()));
+            }
+        }
+    }
+}
+
+impl StatusEffects{
+    pub fn apply(&mut self, effect: Gd<StatusEffect>){
+        let (kind, duration, strength, stacking, max_stacks) = {
+            let e = effect.bind();
+            (e.get_kind(), e.get_duration(), e.get_strength(), e.get_stacking(), e.get_max_stacks())
+        };
+        let mut existing = self.active.iter_mut().filter(|active| active.effect == effect).peekable();
+        match stacking{
+            StackRule::Refresh => {
+                if let Some(active) = existing.next(){
+                    active.remaining = duration;
+                    active.strength = active.strength.max(strength);
+                    return;
+                }
+            }
+            StackRule::Ignore => {
+                if existing.peek().is_some(){
+                    return;
+                }
+            }
+            StackRule::Stack => {
+                if max_stacks > 0 && existing.count() as i32 >= max_stacks{
+                    //replace whichever stack runs out first
+                    if let Some(oldest) = self.active.iter_mut()
+                        .filter(|active| active.effect == effect)
+                        .min_by(|a, b| a.remaining.total_cmp(&b.remaining)){
+                        oldest.remaining = duration;
+                    }
+                    return;
+                }
+            }
+        }
+        self.active.push(ActiveEffect { effect, kind, strength, remaining: duration, tick_timer: 0. });
+    }
+    pub fn is_stunned(&self) -> bool{
+        self.active.iter().any(|active| active.kind == StatusKind::Stun)
+    }
+    //what the enemy's speed gets multiplied by, the strongest slow wins
+    pub fn speed_multiplier(&self) -> f32{
+        if self.is_stunned(){
+            return 0.;
+        }
+        let slow = self.active.iter()
+            .filter(|active| active.kind == StatusKind::Slow)
+            .map(|active| active.strength)
+            .fold(0., f32::max);
+        (1. - slow).clamp(0., 1.)
+    }
+}
@@ -0,0 +1,6 @@
+use godot::builtin::Vector2i;
+
+//shorthand for tile positions in tests
+pub fn p(x: i32, y: i32) -> Vector2i{
+    Vector2i::new(x, y)
+}
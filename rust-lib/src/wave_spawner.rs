@@ -0,0 +1,224 @@
+use godot::builtin::Array;
+use godot::builtin::Variant;
+use godot::classes::INode;
+use godot::classes::Node;
+use godot::classes::PackedScene;
+use godot::classes::Resource;
+use godot::global::godot_warn;
+use godot::obj::Base;
+use godot::obj::Gd;
+use godot::obj::WithBaseField;
+use godot::prelude::godot_api;
+use godot::prelude::GodotClass;
+
+use crate::cell_patterns::EnergyReason;
+use crate::cell_patterns::PlayerEnergy;
+use crate::enemy_spawner::EnemyPath;
+use crate::ingame_state_tracker::GameplayState;
+use crate::ingame_state_tracker::IngameStateTracker;
+use crate::resource_wallet;
+
+//one batch of the same enemy sent down one path
+#[derive(GodotClass)]
+#[class(base = Resource, init)]
+pub struct SpawnGroup{
+    base: Base<Resource>,
+    #[export]
+    enemy_scene: Option<Gd<PackedScene>>,
+    #[export]
+    count: u32,
+    //seconds between spawns
+    #[export]
+    interval: f64,
+    //seconds after the group starts before its first spawn
+    #[export]
+    start_delay: f64,
+    //index into the WaveSpawner's paths
+    #[export]
+    path_index: i32,
+    //true to start once the previous group has spawned everything, false to start alongside it
+    #[export]
+    wait_for_previous: bool,
+    //true if this group is the first of a new wave, the player gets a drawing phase before it
+    #[export]
+    starts_new_wave: bool,
+    //energy given to the player once the group has spawned everything
+    #[export]
+    completion_bonus: i32,
+    //seeds given along with completion_bonus, the only way to earn the seeds perma cells need
+    #[export]
+    seed_bonus: i32
+}
+
+//every group of enemies in a level, in order, split into waves by starts_new_wave
+#[derive(GodotClass)]
+#[class(base = Resource, init)]
+pub struct WaveDefinition{
+    base: Base<Resource>,
+    #[export]
+    groups: Array<Gd<SpawnGroup>>
+}
+
+#[derive(Clone, Copy, Default)]
+struct GroupProgress{
+    started: bool,
+    spawned: u32,
+    next_spawn_in: f64
+}
+
+//runs a WaveDefinition while defending
+#[derive(GodotClass)]
+#[class(base = Node, init)]
+pub struct WaveSpawner{
+    base: Base<Node>,
+    #[export]
+    waves: Option<Gd<WaveDefinition>>,
+    //paths groups can pick from with path_index, the parent is used when this is empty
+    #[export]
+    paths: Array<Gd<EnemyPath>>,
+    #[export]
+    game_state: Option<Gd<IngameStateTracker>>,
+    progress: Vec<GroupProgress>,
+    //wave each group belongs to
+    group_waves: Vec<i32>
+}
+
+#[godot_api]
+impl INode for WaveSpawner{
+    fn ready(&mut self){
+        let groups = self.get_groups();
+        self.progress = vec![GroupProgress::default(); groups.len()];
+        let mut wave = 0;
+        self.group_waves = groups.iter().enumerate().map(|(index, group)| {
+            if index > 0 && group.bind().get_starts_new_wave(){
+                wave += 1;
+            }
+            wave
+        }).collect();
+    }
+    fn physics_process(&mut self, delta: f64){
+        let game_state = self.get_game_state().expect("no game state ref was set");
+        if game_state.bind().get_state() != GameplayState::DEFENDING{
+            return;
+        }
+        let wave = game_state.bind().get_wave();
+        let groups = self.get_groups();
+        for (index, group) in groups.iter().enumerate(){
+            if self.group_waves[index] > wave{
+                break;
+            }
+            let group = group.bind();
+            if !self.progress[index].started{
+                if !self.can_start(index, group.get_wait_for_previous()){
+                    continue;
+                }
+                self.progress[index].started = true;
+                self.progress[index].next_spawn_in = group.get_start_delay();
+                //nothing to wait for, so it's finished as soon as it starts
+                if group.get_count() == 0{
+                    self.finish_group(index as i32, group.get_completion_bonus(), group.get_seed_bonus());
+                    continue;
+                }
+            }
+            if self.progress[index].spawned >= group.get_count(){
+                continue;
+            }
+            self.progress[index].next_spawn_in -= delta;
+            if self.progress[index].next_spawn_in > 0.{
+                continue;
+            }
+            self.progress[index].next_spawn_in += group.get_interval();
+            self.progress[index].spawned += 1;
+            self.spawn(index, &group);
+            if self.progress[index].spawned >= group.get_count(){
+                self.finish_group(index as i32, group.get_completion_bonus(), group.get_seed_bonus());
+            }
+        }
+    }
+}
+
+#[godot_api]
+impl WaveSpawner{
+    #[signal]
+    fn group_finished(index: i32);
+    #[signal]
+    fn spawning_end();
+
+    #[func]
+    pub fn get_group_count(&self) -> i32{
+        self.progress.len() as i32
+    }
+    #[func]
+    pub fn get_wave_count(&self) -> i32{
+        self.group_waves.last().map_or(0, |wave| wave + 1)
+    }
+    //true once every group in the wave has spawned all of its enemies
+    #[func]
+    pub fn is_wave_done(&self, wave: i32) -> bool{
+        let groups = self.get_groups();
+        groups.iter().zip(self.progress.iter()).zip(self.group_waves.iter())
+            .filter(|(_, group_wave)| **group_wave == wave)
+            .all(|((group, progress), _)| Self::is_group_finished(group, progress))
+    }
+    //groups that have spawned all of their enemies
+    #[func]
+    pub fn get_finished_group_count(&self) -> i32{
+        let groups = self.get_groups();
+        groups.iter().zip(self.progress.iter())
+            .filter(|(group, progress)| Self::is_group_finished(group, progress))
+            .count() as i32
+    }
+}
+
+impl WaveSpawner{
+    pub fn is_done(&self) -> bool{
+        self.get_finished_group_count() == self.get_group_count()
+    }
+    fn is_group_finished(group: &Gd<SpawnGroup>, progress: &GroupProgress) -> bool{
+        progress.started && progress.spawned >= group.bind().get_count()
+    }
+    fn get_groups(&self) -> Vec<Gd<SpawnGroup>>{
+        self.waves.as_ref().map_or(vec![], |waves| waves.bind().get_groups().iter_shared().collect())
+    }
+    fn can_start(&self, index: usize, wait_for_previous: bool) -> bool{
+        if index == 0 || self.group_waves[index] != self.group_waves[index - 1]{
+            return true;
+        }
+        let previous = self.progress[index - 1];
+        if !wait_for_previous{
+            return previous.started;
+        }
+        let previous_count = self.get_groups()[index - 1].bind().get_count();
+        previous.started && previous.spawned >= previous_count
+    }
+    //enemies are skipped with a warning when the group's path doesn't exist, they'd have nothing to walk along
+    fn spawn(&self, index: usize, group: &SpawnGroup){
+        let Some(scene) = group.get_enemy_scene() else{
+            return;
+        };
+        let path = if self.paths.is_empty(){
+            self.base().get_parent().and_then(|parent| parent.try_cast::<EnemyPath>().ok())
+        }else{
+            usize::try_from(group.get_path_index()).ok().and_then(|path_index| self.paths.get(path_index))
+        };
+        let Some(mut path) = path else{
+            godot_warn!("spawn group {} has no path at index {}, skipping its enemy", index, group.get_path_index());
+            return;
+        };
+        path.add_child(scene.instantiate().expect("failed to create enemy"));
+    }
+    fn finish_group(&mut self, index: i32, completion_bonus: i32, seed_bonus: i32){
+        if let Some(mut energy) = PlayerEnergy::find(self.base().get_tree().unwrap()){
+            if completion_bonus > 0{
+                energy.bind_mut().gain_energy(completion_bonus, EnergyReason::WaveBonus);
+            }
+            if seed_bonus > 0{
+                energy.bind_mut().gain(resource_wallet::SEEDS, seed_bonus, EnergyReason::WaveBonus);
+            }
+        }
+        self.base_mut().emit_signal("group_finished".into(), &[Variant::from(index)]);
+        if self.is_done(){
+            self.base_mut().emit_signal("spawning_end".into(), &[]);
+        }
+    }
+}